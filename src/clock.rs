@@ -0,0 +1,98 @@
+use std::sync::{atomic::{AtomicBool, Ordering}, Arc, RwLock};
+
+#[cfg(any(test, feature = "test-util"))]
+use std::sync::atomic::AtomicI64;
+
+use chrono::Utc;
+
+use crate::events::EventTime;
+
+// Separate from the writer so events can be stamped while the writer lock is held
+static CLOCK: RwLock<Option<Arc<dyn Clock>>> = RwLock::new(None);
+
+static MICROSECOND_PRECISION: AtomicBool = AtomicBool::new(false);
+
+/// Source of the event timestamps, set with `QlogWriter::set_clock()`
+pub trait Clock: Send + Sync {
+	/// Milliseconds since the UNIX epoch
+	fn now_millis(&self) -> i64;
+
+	/// Microseconds since the UNIX epoch, only used with `TimePrecision::Microseconds`
+	fn now_micros(&self) -> i64 {
+		self.now_millis() * 1000
+	}
+}
+
+/// Precision of the event timestamps, which are always in milliseconds
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TimePrecision {
+	/// Whole milliseconds
+	#[default]
+	Milliseconds,
+	/// Fractional milliseconds (e.g., 1700000000000.123), to see pacing and bursts
+	Microseconds
+}
+
+/// The default clock, uses the system time
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+	fn now_millis(&self) -> i64 {
+		Utc::now().timestamp_millis()
+	}
+
+	fn now_micros(&self) -> i64 {
+		Utc::now().timestamp_micros()
+	}
+}
+
+/// Only moves when told to, makes the timestamps of tests deterministic
+#[cfg(any(test, feature = "test-util"))]
+#[derive(Debug, Default)]
+pub struct ManualClock {
+	millis: AtomicI64
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl ManualClock {
+	pub fn new(millis: i64) -> Self {
+		Self { millis: AtomicI64::new(millis) }
+	}
+
+	pub fn set(&self, millis: i64) {
+		self.millis.store(millis, Ordering::Release);
+	}
+
+	pub fn advance(&self, millis: i64) {
+		self.millis.fetch_add(millis, Ordering::AcqRel);
+	}
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl Clock for ManualClock {
+	fn now_millis(&self) -> i64 {
+		self.millis.load(Ordering::Acquire)
+	}
+}
+
+pub(crate) fn set_clock(clock: Arc<dyn Clock>) {
+	*CLOCK.write().unwrap() = Some(clock);
+}
+
+pub(crate) fn set_time_precision(time_precision: TimePrecision) {
+	MICROSECOND_PRECISION.store(time_precision == TimePrecision::Microseconds, Ordering::Release);
+}
+
+pub(crate) fn now() -> EventTime {
+	let clock = CLOCK.read().unwrap();
+	let clock: &dyn Clock = match clock.as_ref() {
+		Some(clock) => clock.as_ref(),
+		None => &SystemClock
+	};
+
+	match MICROSECOND_PRECISION.load(Ordering::Acquire) {
+		true => EventTime::Micros(clock.now_micros()),
+		false => EventTime::Millis(clock.now_millis())
+	}
+}
@@ -0,0 +1,59 @@
+use std::{fmt::Arguments, sync::{Arc, RwLock}};
+
+/// Receives the diagnostics of the crate, set with `QlogWriter::set_diagnostic_handler()`
+pub type DiagnosticHandler = Arc<dyn Fn(&str) + Send + Sync>;
+
+// Separate from the writer so diagnostics can be reported while the writer lock is held
+static DIAGNOSTIC_HANDLER: RwLock<Option<DiagnosticHandler>> = RwLock::new(None);
+
+pub(crate) fn set_handler(handler: Option<DiagnosticHandler>) {
+	*DIAGNOSTIC_HANDLER.write().unwrap() = handler;
+}
+
+/// Passes the message to the diagnostic handler, printed to stderr when there's no handler
+pub(crate) fn report(message: Arguments) {
+	match DIAGNOSTIC_HANDLER.read().unwrap().as_ref() {
+		Some(handler) => handler(&message.to_string()),
+		None => eprintln!("{message}")
+	}
+}
+
+/// Collects the diagnostics reported on the current thread, so tests running in parallel don't see each other's diagnostics
+#[cfg(test)]
+pub(crate) fn capture<R>(f: impl FnOnce() -> R) -> (R, Vec<String>) {
+	use std::{cell::RefCell, sync::Once};
+
+	thread_local! {
+		static REPORTS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+	}
+
+	static INSTALL_HANDLER: Once = Once::new();
+
+	INSTALL_HANDLER.call_once(|| set_handler(Some(Arc::new(|message| REPORTS.with(|reports| reports.borrow_mut().push(message.to_string()))))));
+
+	REPORTS.with(|reports| reports.borrow_mut().clear());
+
+	let result = f();
+
+	(result, REPORTS.with(|reports| reports.take()))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::thread;
+
+	use super::{capture, report};
+
+	#[test]
+	fn reports_are_captured_per_thread() {
+		let ((), reports) = capture(|| {
+			report(format_args!("first {}", 1));
+
+			thread::spawn(|| report(format_args!("other thread"))).join().unwrap();
+
+			report(format_args!("second"));
+		});
+
+		assert_eq!(reports, ["first 1", "second"]);
+	}
+}
@@ -1,10 +1,9 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
-use chrono::Utc;
-use serde::Serialize;
+use serde::{Serialize, Serializer};
 use serde_with::skip_serializing_none;
 
-use crate::{logfile::TimeFormat, util::{bytes_to_hexstring, is_empty_or_none, GroupId, HexString, PathId, MAX_LOG_DATA_LEN}};
+use crate::{clock, logfile::TimeFormat, util::{bytes_to_hexstring, bytes_to_truncated_hexstring, is_empty_or_none, HexString}};
 
 #[cfg(feature = "moq-transfork")]
 use crate::moq_transfork::{data::*, events::*};
@@ -15,11 +14,14 @@ use crate::moq_transfork::data::StreamType as MoqStreamType;
 use crate::quic_10::{data::*, events::*};
 #[cfg(feature = "quic-10")]
 use crate::quic_10::data::StreamType as QuicStreamType;
+#[cfg(feature = "quic-10")]
+use std::net::SocketAddr;
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Event {
-	time: i64,
+	time: EventTime,
 	name: String,
 	data: ProtocolEventData,
 	#[serde(skip_serializing_if = "is_empty_or_none")]
@@ -27,29 +29,32 @@ pub struct Event {
 	time_format: Option<TimeFormat>,
 	group_id: Option<GroupId>,
 	system_info: Option<SystemInformation>,
+	/// Position of the event in the trace, only set when sequence numbers are enabled on the writer
+	seq: Option<u64>,
+	/// BTreeMap so the flattened fields are always serialized in the same order
 	#[serde(flatten)]
-	custom_fields: HashMap<String, String>
+	custom_fields: BTreeMap<String, String>
 }
 
 impl Event {
     // Assumes default TimeFormat (relative to epoch, epoch = "1970-01-01T00:00:00.000Z")
 	// TODO: Base 'time' value upon chosen TimeFormat
-    #[allow(dead_code)]
-	fn new(event_name: &str, event_data: ProtocolEventData, group_id: Option<String>) -> Self {
-		Self::new_with_time(event_name, event_data, group_id, Utc::now().timestamp_millis())
+	fn new(event_name: &str, event_data: ProtocolEventData, group_id: Option<GroupId>) -> Self {
+		Self::new_with_time(event_name, event_data, group_id, clock::now())
 	}
 
-    fn new_with_time(event_name: &str, event_data: ProtocolEventData, group_id: Option<String>, time: i64) -> Self {
+    fn new_with_time(event_name: &str, event_data: ProtocolEventData, group_id: Option<GroupId>, time: EventTime) -> Self {
         Self {
 			time,
 			name: event_name.to_string(),
 			data: event_data,
 			// TODO: Maybe add a path ID
-			path: Some("".to_string()),
+			path: Some(PathId::from("")),
 			time_format: None,
 			group_id,
 			system_info: None,
-			custom_fields: HashMap::new()
+			seq: None,
+			custom_fields: BTreeMap::new()
 		}
     }
 
@@ -57,30 +62,201 @@ impl Event {
 		&self.name
 	}
 
-	pub fn get_group_id(&self) -> Option<&String> {
+	pub fn get_group_id(&self) -> Option<&GroupId> {
 		self.group_id.as_ref()
 	}
 
-    pub fn set_group_id(&mut self, group_id: Option<&String>) {
+    pub fn set_group_id(&mut self, group_id: Option<&GroupId>) {
 		self.group_id = group_id.cloned();
 	}
+
+	/// The protocol the event's data belongs to
+	pub fn protocol(&self) -> Protocol {
+		match self.data {
+			#[cfg(feature = "moq-transfork")]
+			ProtocolEventData::MoqEventData(_) => Protocol::MoqTransfork,
+			#[cfg(feature = "quic-10")]
+			ProtocolEventData::Quic10EventData(_) => Protocol::Quic10,
+			ProtocolEventData::Custom(_) => Protocol::Custom
+		}
+	}
+
+	/// Event named "namespace:name" with arbitrary data, for events the crate doesn't support (yet)
+	pub fn custom(namespace: &str, name: &str, data: serde_json::Value, group_id: Option<GroupId>) -> Self {
+		Self::new(format!("{namespace}:{name}").as_str(), ProtocolEventData::Custom(data), group_id)
+	}
+
+	pub(crate) fn get_time(&self) -> EventTime {
+		self.time
+	}
+
+	pub(crate) fn set_seq(&mut self, seq: u64) {
+		self.seq = Some(seq);
+	}
+
+	pub(crate) fn set_custom_field(&mut self, name: &str, value: &str) {
+		self.custom_fields.insert(name.to_string(), value.to_string());
+	}
+
+	/// The event as compact (single line) JSON
+	pub fn to_json(&self) -> serde_json::Result<String> {
+		serde_json::to_string(self)
+	}
+
+	/// The event as a JSON text sequence record (RFC 7464)
+	pub fn to_json_seq_record(&self) -> serde_json::Result<String> {
+		Ok(format!("\u{1E}{}\n", self.to_json()?))
+	}
+
+	/// Annotates why the event was emitted with a top-level `trigger` field
+	pub fn set_trigger(&mut self, trigger: &str) {
+		self.set_custom_field("trigger", trigger);
+	}
+}
+
+/// Groups events that belong together (e.g., a QUIC connection ID or a MoQ tracing ID)
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+pub enum GroupId {
+	Number(u64),
+	Text(String)
+}
+
+impl From<u64> for GroupId {
+	fn from(value: u64) -> Self {
+		Self::Number(value)
+	}
+}
+
+impl From<String> for GroupId {
+	fn from(value: String) -> Self {
+		Self::Text(value)
+	}
+}
+
+impl From<&str> for GroupId {
+	fn from(value: &str) -> Self {
+		Self::Text(value.to_string())
+	}
+}
+
+/// The protocols events can be logged for, one variant per enabled protocol feature
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Protocol {
+	#[cfg(feature = "moq-transfork")]
+	MoqTransfork,
+	#[cfg(feature = "quic-10")]
+	Quic10,
+	/// Events created with `Event::custom()`
+	Custom
+}
+
+/// Timestamp of an event, serialized in (fractional) milliseconds
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum EventTime {
+	Millis(i64),
+	Micros(i64)
+}
+
+impl EventTime {
+	/// For comparing timestamps of different precisions
+	pub(crate) fn as_micros(&self) -> i64 {
+		match self {
+			Self::Millis(millis) => millis.saturating_mul(1000),
+			Self::Micros(micros) => *micros
+		}
+	}
+}
+
+impl Serialize for EventTime {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		match self {
+			Self::Millis(millis) => serializer.serialize_i64(*millis),
+			Self::Micros(micros) => serializer.serialize_f64(*micros as f64 / 1000.0)
+		}
+	}
 }
 
-#[derive(Serialize)]
+impl std::fmt::Display for EventTime {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Millis(millis) => write!(f, "{millis}"),
+			Self::Micros(micros) => write!(f, "{}", *micros as f64 / 1000.0)
+		}
+	}
+}
+
+#[cfg(feature = "schemars")]
+impl schemars::JsonSchema for EventTime {
+	fn schema_name() -> String {
+		"EventTime".to_string()
+	}
+
+	fn json_schema(generator: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+		<f64 as schemars::JsonSchema>::json_schema(generator)
+	}
+}
+
+/// Identifies a network path, newer multipath drafts use numeric path IDs
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+pub enum PathId {
+	Number(u64),
+	Text(String)
+}
+
+impl PathId {
+	/// An empty text path ID refers to the default path
+	pub fn is_empty(&self) -> bool {
+		match self {
+			Self::Number(_) => false,
+			Self::Text(text) => text.is_empty()
+		}
+	}
+}
+
+impl From<u64> for PathId {
+	fn from(value: u64) -> Self {
+		Self::Number(value)
+	}
+}
+
+impl From<String> for PathId {
+	fn from(value: String) -> Self {
+		Self::Text(value)
+	}
+}
+
+impl From<&str> for PathId {
+	fn from(value: &str) -> Self {
+		Self::Text(value.to_string())
+	}
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 enum ProtocolEventData {
     #[cfg(feature = "moq-transfork")]
 	MoqEventData(MoqEventData),
 
     #[cfg(feature = "quic-10")]
-	Quic10EventData(Quic10EventData)
+	Quic10EventData(Quic10EventData),
+
+	/// Data of an event created with `Event::custom()`
+	Custom(serde_json::Value)
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct RawInfo {
 	/// The full byte length
 	length: Option<u64>,
+	/// The byte length of the header, lets tools split `data` into the header and the (protected) payload
+	header_length: Option<u64>,
 	/// The byte length of the payload
 	payload_length: Option<u64>,
 	/// The (potentially truncated) contents, including headers and possibly trailers
@@ -94,19 +270,41 @@ impl RawInfo {
 				let payload_length: u64 = payload.len().try_into().unwrap();
 
 				// Only log the first MAX_LOG_DATA_LEN bytes
-				if payload_length > MAX_LOG_DATA_LEN.try_into().unwrap() {
-					let truncated = &payload[..MAX_LOG_DATA_LEN];
-					return Self { length, payload_length: Some(payload_length), data: Some(bytes_to_hexstring(truncated)) };
-				}
+				Self { length, header_length: None, payload_length: Some(payload_length), data: Some(bytes_to_truncated_hexstring(payload)) }
+			},
+			None => Self { length, header_length: None, payload_length: None, data: None }
+		}
+	}
 
-				Self { length, payload_length: Some(payload_length), data: Some(bytes_to_hexstring(payload)) }
+	/// Same as `new()`, but logs up to `max_data_len` bytes of the data instead of the default 64
+	pub fn new_with_max_data_len(length: Option<u64>, data: Option<&[u8]>, max_data_len: usize) -> Self {
+		match data {
+			Some(payload) => {
+				let payload_length: u64 = payload.len().try_into().unwrap();
+
+				Self { length, header_length: None, payload_length: Some(payload_length), data: Some(bytes_to_hexstring(&payload[..payload.len().min(max_data_len)])) }
 			},
-			None => Self { length, payload_length: None, data: None }
+			None => Self { length, header_length: None, payload_length: None, data: None }
 		}
 	}
+
+	/// `data` contains the header followed by the payload, `header_length` marks where the header ends
+	pub fn new_with_header(length: Option<u64>, header_length: u64, data: Option<&[u8]>) -> Self {
+		let mut raw_info = Self::new(length, data);
+
+		raw_info.header_length = Some(header_length);
+		raw_info.payload_length = raw_info.payload_length.map(|total| total.saturating_sub(header_length));
+
+		raw_info
+	}
+
+	pub fn get_length(&self) -> Option<u64> {
+		self.length
+	}
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 struct SystemInformation {
 	processor_id: Option<u32>,
 	process_id: Option<u32>,
@@ -115,9 +313,16 @@ struct SystemInformation {
 
 #[cfg(feature = "moq-transfork")]
 impl Event {
-    fn new_moq(event_name: &str, event_data: MoqEventData, group_id: u64) -> Self {
-        let group_id = group_id.to_string();
-        Self::new(format!("{MOQ_VERSION_STRING}:{event_name}").as_str(), ProtocolEventData::MoqEventData(event_data), Some(group_id))
+    // The tracing ID is kept numeric, so it gets serialized as a JSON number
+    fn new_moq(event_name: &str, event_data: MoqEventData, tracing_id: u64) -> Self {
+        Self::new(format!("{MOQ_VERSION_STRING}:{event_name}").as_str(), ProtocolEventData::MoqEventData(event_data), Some(GroupId::Number(tracing_id)))
+    }
+
+    pub fn moq_tracing_id(&self) -> Option<u64> {
+        match self.group_id {
+            Some(GroupId::Number(tracing_id)) => Some(tracing_id),
+            _ => None
+        }
     }
 
 	pub fn moq_stream_created(stream_type: MoqStreamType, tracing_id: u64) -> Self {
@@ -152,6 +357,14 @@ impl Event {
 		Self::new_moq("session_update_parsed", MoqEventData::SessionUpdateParsed(SessionUpdate::new(session_bitrate)), tracing_id)
 	}
 
+	pub fn moq_session_update_created_with_delta(session_bitrate: u64, session_bitrate_delta: Option<i64>, tracing_id: u64) -> Self {
+		Self::new_moq("session_update_created", MoqEventData::SessionUpdateCreated(SessionUpdate::with_delta(session_bitrate, session_bitrate_delta)), tracing_id)
+	}
+
+	pub fn moq_session_update_parsed_with_delta(session_bitrate: u64, session_bitrate_delta: Option<i64>, tracing_id: u64) -> Self {
+		Self::new_moq("session_update_parsed", MoqEventData::SessionUpdateParsed(SessionUpdate::with_delta(session_bitrate, session_bitrate_delta)), tracing_id)
+	}
+
 	pub fn moq_announce_please_created(track_prefix_parts: Vec<String>, tracing_id: u64) -> Self {
 		Self::new_moq("announce_please_created", MoqEventData::AnnouncePleaseCreated(AnnouncePlease::new(track_prefix_parts)), tracing_id)
 	}
@@ -175,6 +388,13 @@ impl Event {
 		Self::new_moq("subscription_started_created", MoqEventData::SubscriptionStarted(Subscribe::new(subscribe_id, track_path_parts, track_priority, group_order, group_min, group_max)), tracing_id)
 	}
 
+	pub fn moq_subscription_started_created_checked(subscribe_id: u64, track_path_parts: Vec<String>, track_priority: i64, group_order: u64, group_min: Option<u64>, group_max: Option<u64>, tracing_id: u64) -> Self {
+		let group_min = group_min.unwrap_or(0);
+		let group_max = group_max.unwrap_or(0);
+
+		Self::new_moq("subscription_started_created", MoqEventData::SubscriptionStarted(Subscribe::new_checked(subscribe_id, track_path_parts, track_priority, group_order, group_min, group_max)), tracing_id)
+	}
+
 	pub fn moq_subscription_started_parsed(subscribe_id: u64, track_path_parts: Vec<String>, track_priority: i64, group_order: u64, group_min: Option<u64>, group_max: Option<u64>, tracing_id: u64) -> Self {
 		let group_min = group_min.unwrap_or(0);
 		let group_max = group_max.unwrap_or(0);
@@ -182,6 +402,13 @@ impl Event {
 		Self::new_moq("subscription_started_parsed", MoqEventData::SubscriptionStarted(Subscribe::new(subscribe_id, track_path_parts, track_priority, group_order, group_min, group_max)), tracing_id)
 	}
 
+	pub fn moq_subscription_started_parsed_checked(subscribe_id: u64, track_path_parts: Vec<String>, track_priority: i64, group_order: u64, group_min: Option<u64>, group_max: Option<u64>, tracing_id: u64) -> Self {
+		let group_min = group_min.unwrap_or(0);
+		let group_max = group_max.unwrap_or(0);
+
+		Self::new_moq("subscription_started_parsed", MoqEventData::SubscriptionStarted(Subscribe::new_checked(subscribe_id, track_path_parts, track_priority, group_order, group_min, group_max)), tracing_id)
+	}
+
 	pub fn moq_subscription_update_created(track_priority: u64, group_order: u64, group_min: Option<u64>, group_max: Option<u64>, tracing_id: u64) -> Self {
 		let group_min = group_min.unwrap_or(0);
 		let group_max = group_max.unwrap_or(0);
@@ -216,18 +443,34 @@ impl Event {
 		Self::new_moq("info_please_created", MoqEventData::InfoPleaseCreated(InfoPlease::new(track_path_parts)), tracing_id)
 	}
 
+	pub fn moq_info_please_created_checked(track_path_parts: Vec<String>, tracing_id: u64) -> Self {
+		Self::new_moq("info_please_created", MoqEventData::InfoPleaseCreated(InfoPlease::new_checked(track_path_parts)), tracing_id)
+	}
+
 	pub fn moq_info_please_parsed(track_path_parts: Vec<String>, tracing_id: u64) -> Self {
 		Self::new_moq("info_please_parsed", MoqEventData::InfoPleaseParsed(InfoPlease::new(track_path_parts)), tracing_id)
 	}
 
+	pub fn moq_info_please_parsed_checked(track_path_parts: Vec<String>, tracing_id: u64) -> Self {
+		Self::new_moq("info_please_parsed", MoqEventData::InfoPleaseParsed(InfoPlease::new_checked(track_path_parts)), tracing_id)
+	}
+
 	pub fn moq_fetch_created(track_path_parts: Vec<String>, track_priority: i64, group_sequence: u64, frame_sequence: u64, tracing_id: u64) -> Self {
 		Self::new_moq("fetch_created", MoqEventData::FetchCreated(Fetch::new(track_path_parts, track_priority, group_sequence, frame_sequence)), tracing_id)
 	}
 
+	pub fn moq_fetch_created_checked(track_path_parts: Vec<String>, track_priority: i64, group_sequence: u64, frame_sequence: u64, tracing_id: u64) -> Self {
+		Self::new_moq("fetch_created", MoqEventData::FetchCreated(Fetch::new_checked(track_path_parts, track_priority, group_sequence, frame_sequence)), tracing_id)
+	}
+
 	pub fn moq_fetch_parsed(track_path_parts: Vec<String>, track_priority: i64, group_sequence: u64, frame_sequence: u64, tracing_id: u64) -> Self {
 		Self::new_moq("fetch_parsed", MoqEventData::FetchParsed(Fetch::new(track_path_parts, track_priority, group_sequence, frame_sequence)), tracing_id)
 	}
 
+	pub fn moq_fetch_parsed_checked(track_path_parts: Vec<String>, track_priority: i64, group_sequence: u64, frame_sequence: u64, tracing_id: u64) -> Self {
+		Self::new_moq("fetch_parsed", MoqEventData::FetchParsed(Fetch::new_checked(track_path_parts, track_priority, group_sequence, frame_sequence)), tracing_id)
+	}
+
 	pub fn moq_fetch_update_created(track_priority: i64, tracing_id: u64) -> Self {
 		Self::new_moq("fetch_update_created", MoqEventData::FetchUpdateCreated(FetchUpdate::new(track_priority)), tracing_id)
 	}
@@ -252,6 +495,22 @@ impl Event {
 		Self::new_moq("frame_parsed", MoqEventData::FrameParsed(Frame::new(RawInfo::new(payload_length, payload))), tracing_id)
 	}
 
+	pub fn moq_subgroup_created(subscribe_id: u64, group_sequence: u64, subgroup_sequence: u64, tracing_id: u64) -> Self {
+		Self::new_moq("subgroup_created", MoqEventData::SubgroupCreated(Subgroup::new(subscribe_id, group_sequence, subgroup_sequence)), tracing_id)
+	}
+
+	pub fn moq_subgroup_parsed(subscribe_id: u64, group_sequence: u64, subgroup_sequence: u64, tracing_id: u64) -> Self {
+		Self::new_moq("subgroup_parsed", MoqEventData::SubgroupParsed(Subgroup::new(subscribe_id, group_sequence, subgroup_sequence)), tracing_id)
+	}
+
+	pub fn moq_object_created(subscribe_id: u64, group_sequence: u64, subgroup_sequence: u64, object_sequence: u64, payload_length: Option<u64>, payload: Option<&[u8]>, tracing_id: u64) -> Self {
+		Self::new_moq("object_created", MoqEventData::ObjectCreated(Object::new(subscribe_id, group_sequence, subgroup_sequence, object_sequence, RawInfo::new(payload_length, payload))), tracing_id)
+	}
+
+	pub fn moq_object_parsed(subscribe_id: u64, group_sequence: u64, subgroup_sequence: u64, object_sequence: u64, payload_length: Option<u64>, payload: Option<&[u8]>, tracing_id: u64) -> Self {
+		Self::new_moq("object_parsed", MoqEventData::ObjectParsed(Object::new(subscribe_id, group_sequence, subgroup_sequence, object_sequence, RawInfo::new(payload_length, payload))), tracing_id)
+	}
+
 	pub fn moq_get_stream_type(&self) -> Option<&MoqStreamType> {
 		match &self.data {
 			ProtocolEventData::MoqEventData(moq_event) => match moq_event {
@@ -286,19 +545,52 @@ impl Event {
         Self::new(
             format!("{QUIC_10_VERSION_STRING}:{event_name}").as_str(), 
             ProtocolEventData::Quic10EventData(event_data),
-            group_id
+            group_id.map(GroupId::Text)
         )
     }
 
-    pub(crate) fn new_quic_10_with_time(event_name: &str, event_data: Quic10EventData, group_id: Option<String>, time: i64) -> Self {
+    pub(crate) fn new_quic_10_with_time(event_name: &str, event_data: Quic10EventData, group_id: Option<String>, time: EventTime) -> Self {
         Self::new_with_time(
             format!("{QUIC_10_VERSION_STRING}:{event_name}").as_str(), 
             ProtocolEventData::Quic10EventData(event_data),
-            group_id,
+            group_id.map(GroupId::Text),
             time
         )
     }
 
+    /// The number of frames, only for packet_sent, packet_received and frames_processed events
+    pub(crate) fn quic_10_frame_count(&self) -> Option<usize> {
+        match &self.data {
+            ProtocolEventData::Quic10EventData(quic_event) => quic_event.frame_count(),
+            _ => None
+        }
+    }
+
+    pub(crate) fn quic_10_lost_packet_headers(&self) -> Vec<&PacketHeader> {
+        match &self.data {
+            ProtocolEventData::Quic10EventData(quic_event) => quic_event.lost_packet_headers(),
+            _ => Vec::new()
+        }
+    }
+
+    pub(crate) fn quic_10_is_connection_closed(&self) -> bool {
+        matches!(self.data, ProtocolEventData::Quic10EventData(Quic10EventData::ConnectionClosed(_)))
+    }
+
+    pub(crate) fn quic_10_recovery_metrics(&self) -> Option<&RecoveryMetricsUpdated> {
+        match &self.data {
+            ProtocolEventData::Quic10EventData(Quic10EventData::RecoveryMetricsUpdated(metrics)) => Some(metrics),
+            _ => None
+        }
+    }
+
+    /// Keeps only the first `len` frames and marks the event as truncated
+    pub(crate) fn quic_10_truncate_frames(&mut self, len: usize) {
+        if let ProtocolEventData::Quic10EventData(quic_event) = &mut self.data {
+            quic_event.truncate_frames(len);
+        }
+    }
+
     pub fn quic_10_server_listening(ip_v4: Option<IpAddress>, port_v4: Option<u16>, ip_v6: Option<IpAddress>, port_v6: Option<u16>, retry_required: Option<bool>, cid: Option<String>) -> Self {
         Self::new_quic_10(
             "server_listening",
@@ -319,6 +611,15 @@ impl Event {
         )
     }
 
+    /// `local_cids` are the connection IDs chosen by the local endpoint and `remote_cids` the ones chosen by the peer
+    pub fn quic_10_connection_started_from_sockets(local: SocketAddr, remote: SocketAddr, local_cids: Vec<ConnectionId>, remote_cids: Vec<ConnectionId>, cid: Option<String>) -> Self {
+        Self::quic_10_connection_started(
+            PathEndpointInfo::from_socket_addr(local, local_cids),
+            PathEndpointInfo::from_socket_addr(remote, remote_cids),
+            cid
+        )
+    }
+
     pub fn quic_10_connection_closed(
         owner: Option<Owner>,
         connection_code: Option<ConnectionError>,
@@ -346,6 +647,24 @@ impl Event {
         )
     }
 
+    pub fn quic_10_connection_closed_from(connection_closed: ConnectionClosed, cid: Option<String>) -> Self {
+        Self::new_quic_10("connection_closed", Quic10EventData::ConnectionClosed(connection_closed), cid)
+    }
+
+    pub fn quic_10_connection_closed_idle_timeout(owner: Owner, cid: Option<String>) -> Self {
+        Self::quic_10_connection_closed(Some(owner), None, None, None, None, None, Some(ConnectionCloseTrigger::IdleTimeout), cid)
+    }
+
+    /// The application error code goes in code_bytes (no application error names are known)
+    pub fn quic_10_connection_closed_application(owner: Owner, code: u32, reason: Option<String>, cid: Option<String>) -> Self {
+        Self::quic_10_connection_closed(Some(owner), None, Some(ApplicationError::Unknown), Some(code), None, reason, Some(ConnectionCloseTrigger::Application), cid)
+    }
+
+    /// A stateless reset is always received from the peer
+    pub fn quic_10_connection_closed_stateless_reset(cid: Option<String>) -> Self {
+        Self::quic_10_connection_closed(Some(Owner::Remote), None, None, None, None, None, Some(ConnectionCloseTrigger::StatelessReset), cid)
+    }
+
     pub fn quic_10_connection_id_updated(owner: Owner, old: Option<ConnectionId>, new: Option<ConnectionId>, cid: Option<String>) -> Self {
         Self::new_quic_10(
             "connection_id_updated", 
@@ -386,6 +705,26 @@ impl Event {
         )
     }
 
+    pub fn quic_10_path_updated(path_id: PathId, path_remote: Option<PathEndpointInfo>, path_local: Option<PathEndpointInfo>, reason: Option<String>, cid: Option<String>) -> Self {
+        Self::new_quic_10(
+            "path_updated",
+            Quic10EventData::PathUpdated(
+                PathUpdated::new(path_id, path_remote, path_local, reason)
+            ),
+            cid
+        )
+    }
+
+    pub fn quic_10_path_abandoned(path_id: PathId, error_code: Option<u64>, reason: Option<String>, cid: Option<String>) -> Self {
+        Self::new_quic_10(
+            "path_abandoned",
+            Quic10EventData::PathAbandoned(
+                PathAbandoned::new(path_id, error_code, reason)
+            ),
+            cid
+        )
+    }
+
     pub fn quic_10_mtu_updated(old: Option<u32>, new: u32, done: Option<bool>, cid: Option<String>) -> Self {
         Self::new_quic_10(
             "mtu_updated",
@@ -420,7 +759,8 @@ impl Event {
         owner: Option<Owner>,
         resumption_allowed: Option<bool>,
         early_data_enabled: Option<bool>,
-        tls_cipher: Option<String>,
+        tls_cipher: Option<TlsCipher>,
+        key_exchange_group: Option<KeyExchangeGroup>,
         original_destination_connection_id: Option<ConnectionId>,
         initial_source_connection_id: Option<ConnectionId>,
         retry_source_connection_id: Option<ConnectionId>,
@@ -451,6 +791,7 @@ impl Event {
                     resumption_allowed,
                     early_data_enabled,
                     tls_cipher,
+                    key_exchange_group,
                     original_destination_connection_id,
                     initial_source_connection_id,
                     retry_source_connection_id,
@@ -477,6 +818,13 @@ impl Event {
         )
     }
 
+    /// Logs parameters built with `ParametersSet::new()` and reports inconsistent ones (see `ParametersSet::check()`)
+    pub fn quic_10_parameters_set_checked(parameters_set: ParametersSet, cid: Option<String>) -> Self {
+        parameters_set.check();
+
+        Self::new_quic_10("parameters_set", Quic10EventData::ParametersSet(parameters_set), cid)
+    }
+
     pub fn quic_10_parameters_restored(
         disable_active_migration: Option<bool>,
         max_idle_timeout: Option<u64>,
@@ -553,11 +901,21 @@ impl Event {
         )
     }
 
+    /// For a packet assembled with `PacketSent::builder()`
+    pub fn quic_10_packet_sent_from(packet: PacketSent, cid: Option<String>) -> Self {
+        Self::new_quic_10("packet_sent", Quic10EventData::PacketSent(packet), cid)
+    }
+
+    /// For a packet assembled with `PacketReceived::builder()`
+    pub fn quic_10_packet_received_from(packet: PacketReceived, cid: Option<String>) -> Self {
+        Self::new_quic_10("packet_received", Quic10EventData::PacketReceived(packet), cid)
+    }
+
     pub fn quic_10_packet_dropped(
         header: Option<PacketHeader>,
         raw: Option<RawInfo>,
         datagram_id: Option<u32>,
-        details: HashMap<String, Vec<u8>>,
+        details: BTreeMap<String, HexString>,
         trigger: Option<PacketDroppedTrigger>,
         cid: Option<String>
     ) -> Self {
@@ -570,6 +928,24 @@ impl Event {
         )
     }
 
+    /// For a datagram that couldn't be parsed or decrypted, whose header is unknown
+    pub fn quic_10_packet_dropped_raw(datagram_id: Option<u32>, payload: &[u8], trigger: Option<PacketDroppedTrigger>, cid: Option<String>) -> Self {
+        let raw = RawInfo::new(Some(payload.len() as u64), Some(payload));
+
+        Self::quic_10_packet_dropped(None, Some(raw), datagram_id, BTreeMap::new(), trigger, cid)
+    }
+
+    /// Use `RawInfo::new_with_max_data_len()` to keep more than the first `MAX_LOG_DATA_LEN` bytes of the packet
+    pub fn quic_10_raw_packet_captured(raw: RawInfo, datagram_id: Option<u32>, cid: Option<String>) -> Self {
+        Self::new_quic_10(
+            "raw_packet_captured",
+            Quic10EventData::RawPacketCaptured(
+                RawPacketCaptured::new(raw, datagram_id)
+            ),
+            cid
+        )
+    }
+
     pub fn quic_10_packet_buffered(header: Option<PacketHeader>, raw: Option<RawInfo>, datagram_id: Option<u32>, trigger: Option<PacketBufferedTrigger>, cid: Option<String>) -> Self {
         Self::new_quic_10(
             "packet_buffered",
@@ -590,6 +966,16 @@ impl Event {
         )
     }
 
+    pub fn quic_10_packet_number_skipped(packet_number_space: Option<PacketNumberSpace>, packet_number: u64, cid: Option<String>) -> Self {
+        Self::new_quic_10(
+            "packet_number_skipped",
+            Quic10EventData::PacketNumberSkipped(
+                PacketNumberSkipped::new(packet_number_space, packet_number)
+            ),
+            cid
+        )
+    }
+
     pub fn quic_10_udp_datagrams_sent(count: Option<u16>, raw: Option<Vec<RawInfo>>, ecn: Option<Vec<Ecn>>, datagram_ids: Option<Vec<u32>>, cid: Option<String>) -> Self {
         Self::new_quic_10(
             "udp_datagrams_sent",
@@ -640,6 +1026,16 @@ impl Event {
         )
     }
 
+    pub fn quic_10_frames_processed_with_application_frames(frames: Vec<QuicFrame>, packet_numbers: Option<Vec<u64>>, application_frames: Vec<serde_json::Value>, cid: Option<String>) -> Self {
+        let mut frames_processed = FramesProcessed::new(frames, packet_numbers);
+
+        for frame in application_frames {
+            frames_processed.add_application_frame(frame);
+        }
+
+        Self::new_quic_10("frames_processed", Quic10EventData::FramesProcessed(frames_processed), cid)
+    }
+
     pub fn quic_10_stream_data_moved(
         stream_id: Option<u64>,
         offset: Option<u64>,
@@ -669,18 +1065,29 @@ impl Event {
         )
     }
 
+    pub fn quic_10_datagram_dropped(length: Option<u64>, reason: Option<DatagramDroppedReason>, cid: Option<String>) -> Self {
+        Self::new_quic_10(
+            "datagram_dropped",
+            Quic10EventData::DatagramDropped(
+                DatagramDropped::new(length, reason)
+            ),
+            cid
+        )
+    }
+
     pub fn quic_10_migration_state_updated(
         old: Option<MigrationState>,
         new: MigrationState,
         path_id: Option<PathId>,
         path_remote: Option<PathEndpointInfo>,
         path_local: Option<PathEndpointInfo>,
+        trigger: Option<MigrationTrigger>,
         cid: Option<String>
     ) -> Self {
         Self::new_quic_10(
             "migration_state_updated",
             Quic10EventData::MigrationStateUpdated(
-                MigrationStateUpdated::new(old, new, path_id, path_remote, path_local)
+                MigrationStateUpdated::new(old, new, path_id, path_remote, path_local, trigger)
             ),
             cid
         )
@@ -706,6 +1113,16 @@ impl Event {
         )
     }
 
+    pub fn quic_10_key_retired(key_type: KeyType, key: Option<HexString>, key_phase: Option<u64>, trigger: Option<KeyRetireTrigger>, cid: Option<String>) -> Self {
+        Self::new_quic_10(
+            "key_retired",
+            Quic10EventData::KeyRetired(
+                KeyRetired::new(key_type, key, key_phase, trigger)
+            ),
+            cid
+        )
+    }
+
     pub fn quic_10_recovery_parameters_set(
         reordering_threshold: Option<u16>,
         time_threshold: Option<f32>,
@@ -770,6 +1187,11 @@ impl Event {
         )
     }
 
+    /// Logs metrics built with `RecoveryMetricsUpdated::new()`
+    pub fn quic_10_recovery_metrics_updated_from(recovery_metrics_updated: RecoveryMetricsUpdated, cid: Option<String>) -> Self {
+        Self::new_quic_10("recovery_metrics_updated", Quic10EventData::RecoveryMetricsUpdated(recovery_metrics_updated), cid)
+    }
+
     pub fn quic_10_congestion_state_updated(old: Option<String>, new: String, trigger: Option<String>, cid: Option<String>) -> Self {
         Self::new_quic_10(
             "congestion_state_updated",
@@ -790,6 +1212,36 @@ impl Event {
         )
     }
 
+    pub fn quic_10_loss_timer_set(timer_type: Option<TimerType>, packet_number_space: Option<PacketNumberSpace>, delta: f32, cid: Option<String>) -> Self {
+        Self::new_quic_10(
+            "loss_timer_updated",
+            Quic10EventData::LossTimerUpdated(
+                LossTimerUpdated::set(timer_type, packet_number_space, delta)
+            ),
+            cid
+        )
+    }
+
+    pub fn quic_10_loss_timer_expired(timer_type: Option<TimerType>, packet_number_space: Option<PacketNumberSpace>, cid: Option<String>) -> Self {
+        Self::new_quic_10(
+            "loss_timer_updated",
+            Quic10EventData::LossTimerUpdated(
+                LossTimerUpdated::expired(timer_type, packet_number_space)
+            ),
+            cid
+        )
+    }
+
+    pub fn quic_10_loss_timer_cancelled(timer_type: Option<TimerType>, packet_number_space: Option<PacketNumberSpace>, cid: Option<String>) -> Self {
+        Self::new_quic_10(
+            "loss_timer_updated",
+            Quic10EventData::LossTimerUpdated(
+                LossTimerUpdated::cancelled(timer_type, packet_number_space)
+            ),
+            cid
+        )
+    }
+
     pub fn quic_10_packet_lost(header: Option<PacketHeader>, frames: Option<Vec<QuicFrame>>, is_mtu_probe_packet: Option<bool>, trigger: Option<PacketLostTrigger>, cid: Option<String>) -> Self {
         Self::new_quic_10(
             "packet_lost",
@@ -800,6 +1252,16 @@ impl Event {
         )
     }
 
+    pub fn quic_10_packets_lost(headers: Vec<PacketHeader>, trigger: Option<PacketLostTrigger>, cid: Option<String>) -> Self {
+        Self::new_quic_10(
+            "packets_lost",
+            Quic10EventData::PacketsLost(
+                PacketsLost::new(headers, trigger)
+            ),
+            cid
+        )
+    }
+
     pub fn quic_10_marked_for_retransmit(frames: Vec<QuicFrame>, cid: Option<String>) -> Self {
         Self::new_quic_10(
             "marked_for_retransmit",
@@ -1,6 +1,8 @@
 pub mod writer;
 pub mod logfile;
 pub mod events;
+pub mod clock;
+pub mod diagnostic;
 
 #[cfg(feature = "moq-transfork")]
 pub mod moq_transfork;
@@ -8,4 +10,7 @@ pub mod moq_transfork;
 #[cfg(feature = "quic-10")]
 pub mod quic_10;
 
+#[cfg(feature = "schemars")]
+pub mod schema;
+
 mod util;
@@ -1,10 +1,10 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 use chrono::{DateTime, FixedOffset};
 use serde::Serialize;
 use serde_with::skip_serializing_none;
 
-use crate::util::{is_empty_or_none, PathId, GroupId};
+use crate::{events::{GroupId, PathId}, util::{bytes_to_hexstring, is_empty_or_none, HexString}};
 
 #[cfg(feature = "moq-transfork")]
 use crate::moq_transfork::data::MOQ_VERSION_STRING;
@@ -12,7 +12,12 @@ use crate::moq_transfork::data::MOQ_VERSION_STRING;
 #[cfg(feature = "quic-10")]
 use crate::quic_10::data::QUIC_10_VERSION_STRING;
 
-#[derive(Serialize)]
+/// Last qlog version using the `qlog_version` field, understood by tools such as qvis
+pub const LEGACY_QLOG_VERSION: &str = "0.3";
+pub const LEGACY_QLOG_FORMAT: &str = "JSON-SEQ";
+
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct QlogFileSeq {
 	#[serde(flatten)]
 	log_file_details: LogFile,
@@ -23,15 +28,43 @@ impl QlogFileSeq {
 	pub fn new(log_file_details: LogFile, trace: TraceSeq) -> QlogFileSeq {
 		QlogFileSeq { log_file_details, trace }
 	}
+
+	/// The group_id of the common fields, inherited by every event without one
+	pub(crate) fn get_group_id(&self) -> Option<&GroupId> {
+		self.trace.common_fields.as_ref().and_then(|common_fields| common_fields.group_id.as_ref())
+	}
+
+	/// Keeps the legacy fields the caller already set
+	pub(crate) fn ensure_legacy_fields(&mut self) {
+		if self.log_file_details.qlog_version.is_none() {
+			self.log_file_details.set_legacy_fields(None);
+		}
+	}
+
+	/// Copy of the file details with the legacy name of `protocol_types`
+	pub(crate) fn with_legacy_field_names(&self) -> QlogFileSeq {
+		let mut qlog_file_seq = self.clone();
+
+		if let Some(common_fields) = qlog_file_seq.trace.common_fields.as_mut() {
+			common_fields.legacy_protocol_types = common_fields.protocol_types.take();
+		}
+
+		qlog_file_seq
+	}
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct LogFile {
 	/// Identifies the concrete log file schema
 	file_schema: String,
 	/// Indicates the serialization format using a media type
 	serialization_format: String,
+	/// Legacy field from older qlog drafts, only for tools that don't understand `file_schema` yet
+	qlog_version: Option<String>,
+	/// Legacy field from older qlog drafts, only for tools that don't understand `serialization_format` yet
+	qlog_format: Option<String>,
 	title: Option<String>,
 	description: Option<String>
 }
@@ -43,25 +76,37 @@ impl LogFile {
 		LogFile {
 			file_schema: "urn:ietf:params:qlog:file:sequential".to_string(),
 			serialization_format: "application/qlog+json-seq".to_string(),
+			qlog_version: None,
+			qlog_format: None,
 			title,
 			description
 		}
 	}
+
+	/// Also emits the legacy `qlog_version` (defaults to `LEGACY_QLOG_VERSION`) and `qlog_format` fields
+	pub fn set_legacy_fields(&mut self, qlog_version: Option<String>) {
+		self.qlog_version = Some(qlog_version.unwrap_or_else(|| LEGACY_QLOG_VERSION.to_string()));
+		self.qlog_format = Some(LEGACY_QLOG_FORMAT.to_string());
+	}
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct TraceSeq {
 	title: Option<String>,
 	description: Option<String>,
+	configuration: Option<Configuration>,
 	common_fields: Option<CommonFields>,
 	vantage_point: Option<VantagePoint>,
     /// Identifies concrete event namespaces and their associated types
-	event_schemas: Vec<String>
+	event_schemas: Vec<String>,
+	/// Free-form parameters of the (simulated) scenario that produced the trace (e.g., seed, topology, loss model)
+	scenario: Option<BTreeMap<String, serde_json::Value>>
 }
 
 impl TraceSeq {
-	pub fn new(title: Option<String>, description: Option<String>, common_fields: Option<CommonFields>, vantage_point: Option<VantagePoint>) -> TraceSeq {
+	pub fn new(title: Option<String>, description: Option<String>, configuration: Option<Configuration>, common_fields: Option<CommonFields>, vantage_point: Option<VantagePoint>) -> TraceSeq {
         #[allow(unused_mut)]
         let mut event_schemas: Vec<String> = Vec::default();
 
@@ -74,46 +119,105 @@ impl TraceSeq {
 		TraceSeq {
             title,
             description,
+            configuration,
             common_fields,
             vantage_point,
-			event_schemas
+			event_schemas,
+			scenario: None
         }
 	}
+
+	pub fn set_scenario(&mut self, scenario: BTreeMap<String, serde_json::Value>) {
+		self.scenario = Some(scenario);
+	}
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Default, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Configuration {
+	/// Offset in ms (can be negative), used to align multiple traces on a shared timeline
+	time_offset: Option<f64>,
+	/// URIs of the original traces in case this trace was created by merging or modifying them
+	original_uris: Option<Vec<String>>
+}
+
+impl Configuration {
+	pub fn new(time_offset: Option<f64>, original_uris: Option<Vec<String>>) -> Configuration {
+		Configuration { time_offset, original_uris }
+	}
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct CommonFields {
 	#[serde(skip_serializing_if = "is_empty_or_none")]
 	path: Option<PathId>,
 	time_format: Option<TimeFormat>,
 	reference_time: Option<ReferenceTime>,
 	group_id: Option<GroupId>,
+	/// The protocols whose events appear in the trace, so tools can pick the right parser
+	protocol_types: Option<Vec<String>>,
+	/// The protocol_types under their legacy name, only set with `FieldNames::Legacy`
+	#[serde(rename = "protocol_type")]
+	#[cfg_attr(feature = "schemars", schemars(skip))]
+	legacy_protocol_types: Option<Vec<String>>,
+	/// Original destination connection ID of a QUIC connection, to match the client and server traces
+	#[serde(rename = "ODCID")]
+	odcid: Option<HexString>,
 	#[serde(flatten)]						// Adds the custom fields directly to CommonFields when serializing
-	custom_fields: HashMap<String, String>
+	custom_fields: BTreeMap<String, String>
 }
 
 impl CommonFields {
-	pub fn new(path: Option<PathId>, time_format: Option<TimeFormat>, reference_time: Option<ReferenceTime>, group_id: Option<GroupId>, custom_fields: Option<HashMap<String, String>>) -> CommonFields {
+	pub fn new(path: Option<PathId>, time_format: Option<TimeFormat>, reference_time: Option<ReferenceTime>, group_id: Option<GroupId>, custom_fields: Option<BTreeMap<String, String>>) -> CommonFields {
 		let custom_fields = custom_fields.unwrap_or_default();
 
-		CommonFields { path, time_format, reference_time, group_id, custom_fields }
+		CommonFields { path, time_format, reference_time, group_id, protocol_types: Self::enabled_protocol_types(), legacy_protocol_types: None, odcid: None, custom_fields }
+	}
+
+	/// Replaces the protocol types of the enabled features
+	pub fn set_protocol_types(&mut self, protocol_types: Vec<String>) {
+		self.protocol_types = Some(protocol_types);
+	}
+
+	pub fn set_odcid(&mut self, odcid: &[u8]) {
+		self.odcid = Some(bytes_to_hexstring(odcid));
+	}
+
+	fn enabled_protocol_types() -> Option<Vec<String>> {
+		let protocol_types: &[&str] = &[
+			#[cfg(feature = "quic-10")]
+			"QUIC",
+			#[cfg(feature = "moq-transfork")]
+			"MOQT"
+		];
+
+		match protocol_types.is_empty() {
+			true => None,
+			false => Some(protocol_types.iter().map(|protocol_type| protocol_type.to_string()).collect())
+		}
 	}
 }
 
 impl Default for CommonFields {
 	fn default() -> Self {
 		Self {
-			path: Some("".to_string()),
+			path: Some(PathId::from("")),
 			time_format: Some(TimeFormat::default()),
 			reference_time: Some(ReferenceTime::default()),
 			group_id: None,
-			custom_fields: HashMap::new()
+			protocol_types: Self::enabled_protocol_types(),
+			legacy_protocol_types: None,
+			odcid: None,
+			custom_fields: BTreeMap::new()
 		}
 	}
 }
 
-#[derive(Default, Serialize)]
+#[derive(Clone, Debug, Default, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum TimeFormat {
 	/// Relative to the ReferenceTime 'epoch' field
@@ -124,7 +228,8 @@ pub enum TimeFormat {
 }
 
 #[skip_serializing_none]
-#[derive(Default, Serialize)]
+#[derive(Clone, Debug, Default, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ReferenceTime {
 	clock_type: ClockType,
 	epoch: Epoch,
@@ -145,9 +250,20 @@ impl ReferenceTime {
 
 		ReferenceTime { clock_type, epoch, wall_clock_time }
 	}
+
+	/// Monotonic clock, the epoch is always 'unknown'
+	pub fn monotonic(wall_clock_time: Option<DateTime<FixedOffset>>) -> ReferenceTime {
+		ReferenceTime { clock_type: ClockType::Monotonic, epoch: Epoch::Unknown, wall_clock_time }
+	}
+
+	/// System (wall) clock, epoch defaults to "1970-01-01T00:00:00.000Z" when None
+	pub fn system(epoch: Option<Epoch>, wall_clock_time: Option<DateTime<FixedOffset>>) -> ReferenceTime {
+		ReferenceTime { clock_type: ClockType::System, epoch: epoch.unwrap_or_default(), wall_clock_time }
+	}
 }
 
-#[derive(Default, PartialEq, Eq, Serialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum ClockType {
 	#[default]
@@ -156,7 +272,8 @@ pub enum ClockType {
 	Other(String)
 }
 
-#[derive(PartialEq, Eq, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case", untagged)]
 pub enum Epoch {
 	Rfc3339DateTime(DateTime<FixedOffset>),
@@ -169,9 +286,10 @@ impl Default for Epoch {
 	}
 }
 
-/// Vantage point from which a trace originates
+/// Vantage point from which a trace originates (a trace has a single one)
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct VantagePoint {
 	name: Option<String>,
 	// 'type' is a keyword in Rust
@@ -183,15 +301,55 @@ pub struct VantagePoint {
 
 impl VantagePoint {
 	pub fn new(name: Option<String>, vp_type: VantagePointType, flow: Option<VantagePointType>) -> VantagePoint {
+		match Self::try_new(name, vp_type, flow) {
+			Ok(vantage_point) => vantage_point,
+			Err(e) => panic!("{e}")
+		}
+	}
+
+	/// Same as `new()`, but returns an error instead of panicking
+	pub fn try_new(name: Option<String>, vp_type: VantagePointType, flow: Option<VantagePointType>) -> Result<VantagePoint, String> {
 		if vp_type == VantagePointType::Network && flow.is_none() {
-  			panic!("The 'flow' field is required if the type is 'network'");
-  		}
+			return Err("The 'flow' field is required if the type is 'network'".to_string());
+		}
+
+		Ok(VantagePoint { name, vp_type, flow })
+	}
 
-		VantagePoint { name, vp_type, flow }
+	pub fn client(name: Option<String>) -> VantagePoint {
+		VantagePoint { name, vp_type: VantagePointType::Client, flow: None }
+	}
+
+	pub fn server(name: Option<String>) -> VantagePoint {
+		VantagePoint { name, vp_type: VantagePointType::Server, flow: None }
+	}
+
+	/// Observer in between client and server of the data going in the direction of `flow`
+	pub fn network(name: Option<String>, flow: NetworkFlow) -> VantagePoint {
+		VantagePoint { name, vp_type: VantagePointType::Network, flow: Some(flow.into()) }
+	}
+}
+
+/// The flow of a network vantage point, which can't be another network vantage point
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetworkFlow {
+	Client,
+	Server,
+	Unknown
+}
+
+impl From<NetworkFlow> for VantagePointType {
+	fn from(value: NetworkFlow) -> Self {
+		match value {
+			NetworkFlow::Client => Self::Client,
+			NetworkFlow::Server => Self::Server,
+			NetworkFlow::Unknown => Self::Unknown
+		}
 	}
 }
 
-#[derive(PartialEq, Eq, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum VantagePointType {
 	/// Initiates the connection
@@ -5,7 +5,8 @@ use super::events::*;
 // TODO: Change MoQ event space (this is a placeholder)
 pub const MOQ_VERSION_STRING: &str = "moq-transfork-03";
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 pub enum MoqEventData {
 	StreamCreated(Stream),
@@ -33,10 +34,15 @@ pub enum MoqEventData {
 	GroupCreated(Group),
 	GroupParsed(Group),
 	FrameCreated(Frame),
-	FrameParsed(Frame)
+	FrameParsed(Frame),
+	SubgroupCreated(Subgroup),
+	SubgroupParsed(Subgroup),
+	ObjectCreated(Object),
+	ObjectParsed(Object)
 }
 
-#[derive(PartialEq, Eq, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum StreamType {
 	Session,
@@ -47,14 +53,16 @@ pub enum StreamType {
 	Group
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 pub enum SessionMessage {
 	SessionClient(SessionClient),
 	SessionServer(SessionServer)
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum AnnounceStatus {
 	/// Path is no longer available
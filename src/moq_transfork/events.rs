@@ -1,10 +1,12 @@
 use serde::Serialize;
+use serde_with::skip_serializing_none;
 
 use crate::events::RawInfo;
 
 use super::data::{AnnounceStatus, StreamType};
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Stream {
 	stream_type: StreamType
 }
@@ -19,7 +21,8 @@ impl Stream {
     }
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct SessionClient {
 	supported_versions: Vec<u64>,
 	extension_ids: Vec<u64>,
@@ -34,7 +37,8 @@ impl SessionClient {
 	}
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct SessionServer {
 	selected_version: u64,
 	extension_ids: Vec<u64>
@@ -48,18 +52,28 @@ impl SessionServer {
 	}
 }
 
-#[derive(Serialize)]
+#[skip_serializing_none]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct SessionUpdate {
-	session_bitrate: u64
+	session_bitrate: u64,
+	/// Change compared to the previous update of the session
+	session_bitrate_delta: Option<i64>
 }
 
 impl SessionUpdate {
 	pub fn new(session_bitrate: u64) -> Self {
-		Self { session_bitrate }
+		Self { session_bitrate, session_bitrate_delta: None }
+	}
+
+	pub fn with_delta(session_bitrate: u64, session_bitrate_delta: Option<i64>) -> Self {
+		Self { session_bitrate, session_bitrate_delta }
 	}
 }
 
-#[derive(Serialize)]
+// An empty track prefix is valid (it requests all announcements), so AnnouncePlease has no checked constructor
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct AnnouncePlease {
 	track_prefix_parts: Vec<String>
 }
@@ -70,7 +84,8 @@ impl AnnouncePlease {
 	}
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Announce {
 	announce_status: AnnounceStatus,
 	track_suffix_parts: Vec<Vec<String>>
@@ -82,7 +97,8 @@ impl Announce {
 	}
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Subscribe {
 	subscribe_id: u64,
 	track_path_parts: Vec<String>,
@@ -96,9 +112,17 @@ impl Subscribe {
 	pub fn new(subscribe_id: u64, track_path_parts: Vec<String>, track_priority: i64, group_order: u64, group_min: u64, group_max: u64) -> Self {
 		Self { subscribe_id, track_path_parts, track_priority, group_order, group_min, group_max }
 	}
+
+	/// Same as `new`, but panics when the track path is empty
+	pub fn new_checked(subscribe_id: u64, track_path_parts: Vec<String>, track_priority: i64, group_order: u64, group_min: u64, group_max: u64) -> Self {
+		check_track_path_parts(&track_path_parts);
+
+		Self::new(subscribe_id, track_path_parts, track_priority, group_order, group_min, group_max)
+	}
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct SubscribeUpdate {
 	track_priority: u64,
 	group_order: u64,
@@ -112,7 +136,8 @@ impl SubscribeUpdate {
 	}
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct SubscribeGap {
 	group_start: u64,
 	group_count: u64,
@@ -125,7 +150,8 @@ impl SubscribeGap {
 	}
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Info {
 	track_priority: i64,
 	group_latest: u64,
@@ -138,7 +164,8 @@ impl Info {
 	}
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct InfoPlease {
 	track_path_parts: Vec<String>
 }
@@ -147,9 +174,17 @@ impl InfoPlease {
 	pub fn new(track_path_parts: Vec<String>) -> Self {
 		Self { track_path_parts }
 	}
+
+	/// Same as `new`, but panics when the track path is empty
+	pub fn new_checked(track_path_parts: Vec<String>) -> Self {
+		check_track_path_parts(&track_path_parts);
+
+		Self::new(track_path_parts)
+	}
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Fetch {
 	track_path_parts: Vec<String>,
 	track_priority: i64,
@@ -161,9 +196,17 @@ impl Fetch {
 	pub fn new(track_path_parts: Vec<String>, track_priority: i64, group_sequence: u64, frame_sequence: u64) -> Self {
 		Self { track_path_parts, track_priority, group_sequence, frame_sequence }
 	}
+
+	/// Same as `new`, but panics when the track path is empty
+	pub fn new_checked(track_path_parts: Vec<String>, track_priority: i64, group_sequence: u64, frame_sequence: u64) -> Self {
+		check_track_path_parts(&track_path_parts);
+
+		Self::new(track_path_parts, track_priority, group_sequence, frame_sequence)
+	}
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct FetchUpdate {
 	track_priority: i64
 }
@@ -174,7 +217,8 @@ impl FetchUpdate {
 	}
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Group {
 	subscribe_id: u64,
 	group_sequence: u64
@@ -186,7 +230,38 @@ impl Group {
 	}
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Subgroup {
+	subscribe_id: u64,
+	group_sequence: u64,
+	subgroup_sequence: u64
+}
+
+impl Subgroup {
+	pub fn new(subscribe_id: u64, group_sequence: u64, subgroup_sequence: u64) -> Self {
+		Self { subscribe_id, group_sequence, subgroup_sequence }
+	}
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Object {
+	subscribe_id: u64,
+	group_sequence: u64,
+	subgroup_sequence: u64,
+	object_sequence: u64,
+	payload: RawInfo
+}
+
+impl Object {
+	pub fn new(subscribe_id: u64, group_sequence: u64, subgroup_sequence: u64, object_sequence: u64, payload: RawInfo) -> Self {
+		Self { subscribe_id, group_sequence, subgroup_sequence, object_sequence, payload }
+	}
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Frame {
 	payload: RawInfo
 }
@@ -196,3 +271,10 @@ impl Frame {
 		Self { payload }
 	}
 }
+
+/// A track without any path parts can't be identified, which is almost always a bug
+fn check_track_path_parts(track_path_parts: &[String]) {
+	if track_path_parts.is_empty() {
+		panic!("The track path must contain at least one part");
+	}
+}
@@ -0,0 +1,38 @@
+use std::collections::HashMap;
+
+use crate::events::Event;
+
+/// Remembers the last logged bitrate of every session (keyed by tracing ID) to add the change to session_update events
+#[derive(Default)]
+pub struct SessionBitrateTracker {
+	bitrates: HashMap<u64, u64>
+}
+
+impl SessionBitrateTracker {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Stores the new bitrate of the session and returns the change (None if the session wasn't tracked yet)
+	pub fn update(&mut self, session_bitrate: u64, tracing_id: u64) -> Option<i64> {
+		self.bitrates
+			.insert(tracing_id, session_bitrate)
+			.map(|old| (i128::from(session_bitrate) - i128::from(old)).clamp(i64::MIN.into(), i64::MAX.into()) as i64)
+	}
+
+	/// Returns None when the bitrate didn't change since the previous update
+	pub fn session_update_created(&mut self, session_bitrate: u64, tracing_id: u64) -> Option<Event> {
+		match self.update(session_bitrate, tracing_id) {
+			Some(0) => None,
+			delta => Some(Event::moq_session_update_created_with_delta(session_bitrate, delta, tracing_id))
+		}
+	}
+
+	/// Returns None when the bitrate didn't change since the previous update
+	pub fn session_update_parsed(&mut self, session_bitrate: u64, tracing_id: u64) -> Option<Event> {
+		match self.update(session_bitrate, tracing_id) {
+			Some(0) => None,
+			delta => Some(Event::moq_session_update_parsed_with_delta(session_bitrate, delta, tracing_id))
+		}
+	}
+}
@@ -1,15 +1,16 @@
-use std::{collections::HashMap, fmt::Debug, io::Result, net::{IpAddr, SocketAddr}};
+use std::{collections::BTreeMap, io::Result, net::{IpAddr, SocketAddr}};
 
 use serde::Serialize;
 use serde_with::skip_serializing_none;
 
-use crate::{events::RawInfo, util::HexString};
+use crate::{diagnostic, events::RawInfo, util::{bytes_to_hexstring, bytes_to_truncated_hexstring, serialize_finite_f32, HexString}};
 
 use super::events::*;
 
 pub const QUIC_10_VERSION_STRING: &str = "quic-10";
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 pub enum Quic10EventData {
     ServerListening(ServerListening),
@@ -19,6 +20,8 @@ pub enum Quic10EventData {
     SpinBitUpdated(SpinBitUpdated),
     ConnectionStateUpdated(ConnectionStateUpdated),
     PathAssigned(PathAssigned),
+    PathUpdated(PathUpdated),
+    PathAbandoned(PathAbandoned),
     MtuUpdated(MtuUpdated),
     VersionInformation(VersionInformation),
     AlpnInformation(AlpnInformation),
@@ -27,8 +30,10 @@ pub enum Quic10EventData {
     PacketSent(PacketSent),
     PacketReceived(PacketReceived),
     PacketDropped(PacketDropped),
+    RawPacketCaptured(RawPacketCaptured),
     PacketBuffered(PacketBuffered),
     PacketsAcked(PacketsAcked),
+    PacketNumberSkipped(PacketNumberSkipped),
     UdpDatagramsSent(UdpDatagramsSent),
     UdpDatagramsReceived(UdpDatagramsReceived),
     UdpDatagramDropped(UdpDatagramDropped),
@@ -36,22 +41,80 @@ pub enum Quic10EventData {
     FramesProcessed(FramesProcessed),
     StreamDataMoved(StreamDataMoved),
     DatagramDataMoved(DatagramDataMoved),
+    DatagramDropped(DatagramDropped),
     MigrationStateUpdated(MigrationStateUpdated),
     KeyUpdated(KeyUpdated),
     KeyDiscarded(KeyDiscarded),
+    KeyRetired(KeyRetired),
     RecoveryParametersSet(RecoveryParametersSet),
     RecoveryMetricsUpdated(RecoveryMetricsUpdated),
     CongestionStateUpdated(CongestionStateUpdated),
     LossTimerUpdated(LossTimerUpdated),
     PacketLost(PacketLost),
+    PacketsLost(PacketsLost),
     MarkedForRetransmit(MarkedForRetransmit),
     EcnStateUpdated(EcnStateUpdated)
 }
 
+impl Quic10EventData {
+    /// The number of frames, only for events that carry a list of frames
+    pub(crate) fn frame_count(&self) -> Option<usize> {
+        match self {
+            Self::PacketSent(packet_sent) => Some(packet_sent.frame_count()),
+            Self::PacketReceived(packet_received) => Some(packet_received.frame_count()),
+            Self::FramesProcessed(frames_processed) => Some(frames_processed.frame_count()),
+            _ => None
+        }
+    }
+
+    /// The headers of the packets declared lost, only for packet_lost and packets_lost events
+    pub(crate) fn lost_packet_headers(&self) -> Vec<&PacketHeader> {
+        match self {
+            Self::PacketLost(packet_lost) => packet_lost.get_header().into_iter().collect(),
+            Self::PacketsLost(packets_lost) => packets_lost.get_headers().iter().collect(),
+            _ => Vec::new()
+        }
+    }
+
+    pub(crate) fn truncate_frames(&mut self, len: usize) {
+        match self {
+            Self::PacketSent(packet_sent) => packet_sent.truncate_frames(len),
+            Self::PacketReceived(packet_received) => packet_received.truncate_frames(len),
+            Self::FramesProcessed(frames_processed) => frames_processed.truncate_frames(len),
+            _ => {}
+        }
+    }
+}
+
 pub type QuicVersion = HexString;
+
+/// The version as 8 uppercase hex digits (e.g., "00000001" for QUIC version 1)
+pub fn quic_version_from_u32(version: u32) -> QuicVersion {
+    format!("{version:08X}")
+}
 pub type ConnectionId = HexString;
 
-#[derive(Serialize)]
+/// The largest value of a QUIC variable-length integer (2^62 - 1)
+pub const MAX_VARINT: u64 = (1 << 62) - 1;
+
+/// Catches values that can't be encoded on the wire (i.e., encoder bugs) at logging time
+fn debug_assert_varint(field: &str, value: u64) {
+    debug_assert!(value <= MAX_VARINT, "The {field} ({value}) doesn't fit in a QUIC variable-length integer");
+}
+
+/// None (and reported as a diagnostic) if the connection ID is longer than 255 bytes
+fn connection_id_length(connection_id: &[u8]) -> Option<u8> {
+    match u8::try_from(connection_id.len()) {
+        Ok(length) => Some(length),
+        Err(_) => {
+            diagnostic::report(format_args!("A connection ID can't be longer than 255 bytes, got {} bytes, leaving out its length", connection_id.len()));
+            None
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum Owner {
     Local,
@@ -63,7 +126,8 @@ pub type IpAddress = String;
 
 /// Single half/direction of a path. A full path is comprised of two halves. Firstly: the server sends to the remote client IP + port using a specific destination Connection ID. Secondly: the client sends to the remote server IP + port using a different destination Connection ID.
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct PathEndpointInfo {
     ip_v4: Option<IpAddress>,
     port_v4: Option<u16>,
@@ -78,6 +142,15 @@ impl PathEndpointInfo {
     pub fn new(ip_v4: Option<IpAddress>, port_v4: Option<u16>, ip_v6: Option<IpAddress>, port_v6: Option<u16>, connection_ids: Vec<ConnectionId>) -> Self {
         Self { ip_v4, port_v4, ip_v6, port_v6, connection_ids }
     }
+
+    pub fn from_socket_addr(socket_addr: SocketAddr, connection_ids: Vec<ConnectionId>) -> Self {
+        if socket_addr.is_ipv4() {
+            Self::new(Some(socket_addr.ip().to_string()), Some(socket_addr.port()), None, None, connection_ids)
+        }
+        else {
+            Self::new(None, None, Some(socket_addr.ip().to_string()), Some(socket_addr.port()), connection_ids)
+        }
+    }
 }
 
 // TODO: See what to do with the `connection_ids`
@@ -105,12 +178,7 @@ impl From<Option<IpAddr>> for PathEndpointInfo {
 // TODO: See what to do with the `connection_ids`
 impl From<SocketAddr> for PathEndpointInfo {
     fn from(value: SocketAddr) -> Self {
-        if value.is_ipv4() {
-            Self::new(Some(value.ip().to_string()), Some(value.port()), None, None, Vec::default())
-        }
-        else {
-            Self::new(None, None, Some(value.ip().to_string()), Some(value.port()), Vec::default())
-        }
+        Self::from_socket_addr(value, Vec::default())
     }
 }
 
@@ -124,7 +192,8 @@ impl From<Result<SocketAddr>> for PathEndpointInfo {
     }
 }
 
-#[derive(PartialEq, Eq, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum PacketType {
     Initial,
@@ -139,7 +208,8 @@ pub enum PacketType {
     Unknown
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum PacketNumberSpace {
     Initial,
@@ -149,7 +219,8 @@ pub enum PacketNumberSpace {
 
 /// If the packet_type numerical value does not map to a known packet_type string, the packet_type value of "unknown" can be used and the raw value captured in the packet_type_bytes field; a numerical value without variable-length integer encoding.
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct PacketHeader {
     quic_bit: bool,
     packet_type: PacketType,
@@ -207,6 +278,9 @@ impl PacketHeader {
             panic!("When the packet_type is 'initial', 'handshake', or '0RTT', provide a value for length");
         }
 
+        Self::check_cid_length("scil", scil, "scid", scid.as_ref());
+        Self::check_cid_length("dcil", dcil, "dcid", dcid.as_ref());
+
         Self {
             quic_bit,
             packet_type,
@@ -223,6 +297,53 @@ impl PacketHeader {
         }
     }
 
+    /// Hex-encodes the connection IDs, `scil` and `dcil` are taken from their lengths
+    pub fn long_header(
+        quic_bit: Option<bool>,
+        packet_type: PacketType,
+        packet_type_bytes: Option<u64>,
+        packet_number: Option<u64>,
+        flags: Option<u8>,
+        token: Option<Token>,
+        length: Option<u16>,
+        version: Option<QuicVersion>,
+        scid: &[u8],
+        dcid: &[u8]
+    ) -> Self {
+        Self::new(
+            quic_bit,
+            packet_type,
+            packet_type_bytes,
+            packet_number,
+            flags,
+            token,
+            length,
+            version,
+            connection_id_length(scid),
+            connection_id_length(dcid),
+            Some(bytes_to_hexstring(scid)),
+            Some(bytes_to_hexstring(dcid))
+        )
+    }
+
+    pub fn get_packet_type(&self) -> &PacketType {
+        &self.packet_type
+    }
+
+    pub fn get_packet_number(&self) -> Option<u64> {
+        self.packet_number
+    }
+
+    /// Reports a length that disagrees with the connection ID as a diagnostic, both are logged as given
+    fn check_cid_length(length_name: &str, length: Option<u8>, cid_name: &str, cid: Option<&ConnectionId>) {
+        if let (Some(length), Some(cid)) = (length, cid) {
+            // Connection IDs are hex-encoded, two characters per byte
+            if usize::from(length) * 2 != cid.len() {
+                diagnostic::report(format_args!("The {length_name} ({length}) doesn't match the length of the {cid_name} ({} bytes)", cid.len() / 2));
+            }
+        }
+    }
+
     pub fn update_packet_length(&mut self, payload_length: u16) {
         let packet_num_length = match self.length {
             Some(length) => length,
@@ -230,45 +351,152 @@ impl PacketHeader {
             None => return,
         };
 
-        self.length = Some(packet_num_length + payload_length)
+        // Saturates instead of wrapping around, a wrapped length would look valid in the trace
+        let length = match packet_num_length.checked_add(payload_length) {
+            Some(length) => length,
+            None => {
+                diagnostic::report(format_args!("Packet length overflows ({packet_num_length} + {payload_length}), logging the maximum length instead"));
+                u16::MAX
+            }
+        };
+
+        self.length = Some(length)
     }
 }
 
 // The token carried in an Initial packet can either be a retry token from a Retry packet, or one originally provided by the server in a NEW_TOKEN frame used when resuming a connection (e.g., for address validation purposes). Retry and resumption tokens typically contain encoded metadata to check the token's validity when it is used, but this metadata and its format is implementation specific. For that, Token includes a general-purpose details field.
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Token {
     #[serde(rename = "type")]
     token_type: Option<TokenType>,
 
     /// Decoded fields included in the token (typically: peer's IP address, creation time).
-    // TODO: Check if HashMap typing is correct
+    // TODO: Check if map typing is correct
     #[serde(flatten)]
-    details: HashMap<String, String>,
+    details: BTreeMap<String, String>,
 
     raw: Option<RawInfo>
 }
 
 impl Token {
-    pub fn new(token_type: Option<TokenType>, details: Option<HashMap<String, String>>, raw: Option<RawInfo>) -> Self {
+    pub fn new(token_type: Option<TokenType>, details: Option<BTreeMap<String, String>>, raw: Option<RawInfo>) -> Self {
         let details = details.unwrap_or_default();
 
         Self { token_type, details, raw }
     }
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum TokenType {
     Retry,
     Resumption
 }
 
-// Size = 16
-// The stateless reset token is carried in stateless reset packets, in transport parameters and in NEW_CONNECTION_ID frames.
-pub type StatelessResetToken = HexString;
+/// Always 16 bytes, serialized as a hex string
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(transparent)]
+pub struct StatelessResetToken(HexString);
+
+impl StatelessResetToken {
+    pub const LEN: usize = 16;
+
+    pub fn from_bytes(token: &[u8; Self::LEN]) -> Self {
+        Self(bytes_to_hexstring(token))
+    }
+}
+
+impl TryFrom<&str> for StatelessResetToken {
+    type Error = String;
 
-#[derive(Serialize)]
+    /// Accepts a hex string of exactly 16 bytes
+    fn try_from(value: &str) -> std::result::Result<Self, Self::Error> {
+        if value.len() != Self::LEN * 2 || !value.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("A stateless reset token must be 16 bytes as a hex string (32 characters), got '{value}'"));
+        }
+
+        Ok(Self(value.to_ascii_uppercase()))
+    }
+}
+
+/// TLS 1.3 cipher suites (RFC 8446 Appendix B.4)
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum TlsCipher {
+    #[serde(rename = "AES_128_GCM_SHA256")]
+    Aes128GcmSha256,
+    #[serde(rename = "AES_256_GCM_SHA384")]
+    Aes256GcmSha384,
+    #[serde(rename = "CHACHA20_POLY1305_SHA256")]
+    Chacha20Poly1305Sha256,
+    #[serde(rename = "AES_128_CCM_SHA256")]
+    Aes128CcmSha256,
+    #[serde(rename = "AES_128_CCM_8_SHA256")]
+    Aes128Ccm8Sha256,
+    #[serde(untagged)]
+    Other(String)
+}
+
+impl From<&str> for TlsCipher {
+    /// Accepts the names with or without the "TLS_" prefix, in any case
+    fn from(value: &str) -> Self {
+        let name = value.to_ascii_uppercase();
+
+        match name.strip_prefix("TLS_").unwrap_or(&name) {
+            "AES_128_GCM_SHA256" => Self::Aes128GcmSha256,
+            "AES_256_GCM_SHA384" => Self::Aes256GcmSha384,
+            "CHACHA20_POLY1305_SHA256" => Self::Chacha20Poly1305Sha256,
+            "AES_128_CCM_SHA256" => Self::Aes128CcmSha256,
+            "AES_128_CCM_8_SHA256" => Self::Aes128Ccm8Sha256,
+            _ => Self::Other(value.to_string())
+        }
+    }
+}
+
+/// Key exchange groups of the TLS handshake, named as in the IANA TLS Supported Groups registry
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum KeyExchangeGroup {
+    #[serde(rename = "secp256r1")]
+    Secp256r1,
+    #[serde(rename = "secp384r1")]
+    Secp384r1,
+    #[serde(rename = "secp521r1")]
+    Secp521r1,
+    #[serde(rename = "x25519")]
+    X25519,
+    #[serde(rename = "x448")]
+    X448,
+    #[serde(rename = "X25519MLKEM768")]
+    X25519MlKem768,
+    #[serde(untagged)]
+    Other(String)
+}
+
+impl From<&str> for KeyExchangeGroup {
+    /// Accepts the names in any case
+    fn from(value: &str) -> Self {
+        match value.to_ascii_lowercase().as_str() {
+            "secp256r1" => Self::Secp256r1,
+            "secp384r1" => Self::Secp384r1,
+            "secp521r1" => Self::Secp521r1,
+            "x25519" => Self::X25519,
+            "x448" => Self::X448,
+            "x25519mlkem768" => Self::X25519MlKem768,
+            _ => Self::Other(value.to_string())
+        }
+    }
+}
+
+/// Size of the Retry Integrity Tag in bytes (RFC 9001 Section 5.8)
+pub const RETRY_INTEGRITY_TAG_LEN: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum KeyType {
     ServerInitialSecret,
@@ -285,7 +513,8 @@ pub enum KeyType {
     ClientOneRttSecret,
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum Ecn {
     #[serde(rename = "Not-ECT")]
     NotEct,
@@ -297,13 +526,28 @@ pub enum Ecn {
     Ce
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 pub enum QuicFrame {
     QuicBaseFrame(QuicBaseFrame)
 }
 
-#[derive(Serialize)]
+impl QuicFrame {
+    pub fn frame_type(&self) -> FrameType {
+        match self {
+            Self::QuicBaseFrame(frame) => frame.frame_type()
+        }
+    }
+
+    /// Every frame except ACK, PADDING and CONNECTION_CLOSE is ack-eliciting (RFC 9002 Section 2)
+    pub fn is_ack_eliciting(&self) -> bool {
+        !matches!(self.frame_type(), FrameType::Ack | FrameType::Padding | FrameType::ConnectionClose)
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 pub enum QuicBaseFrame {
     PaddingFrame(PaddingFrame),
@@ -327,39 +571,42 @@ pub enum QuicBaseFrame {
     ConnectionCloseFrame(ConnectionCloseFrame),
     HandshakeDoneFrame(HandshakeDoneFrame),
     UnknownFrame(UnknownFrame),
-    DatagramFrame(DatagramFrame)
+    DatagramFrame(DatagramFrame),
+    ResetStreamAtFrame(ResetStreamAtFrame)
 }
 
-impl Debug for QuicBaseFrame {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl QuicBaseFrame {
+    pub fn frame_type(&self) -> FrameType {
         match self {
-            Self::PaddingFrame(_) => f.debug_tuple("PaddingFrame").finish(),
-            Self::PingFrame(_) => f.debug_tuple("PingFrame").finish(),
-            Self::AckFrame(_) => f.debug_tuple("AckFrame").finish(),
-            Self::ResetStreamFrame(_) => f.debug_tuple("ResetStreamFrame").finish(),
-            Self::StopSendingFrame(_) => f.debug_tuple("StopSendingFrame").finish(),
-            Self::CryptoFrame(_) => f.debug_tuple("CryptoFrame").finish(),
-            Self::NewTokenFrame(_) => f.debug_tuple("NewTokenFrame").finish(),
-            Self::StreamFrame(_) => f.debug_tuple("StreamFrame").finish(),
-            Self::MaxDataFrame(_) => f.debug_tuple("MaxDataFrame").finish(),
-            Self::MaxStreamDataFrame(_) => f.debug_tuple("MaxStreamDataFrame").finish(),
-            Self::MaxStreamsFrame(_) => f.debug_tuple("MaxStreamsFrame").finish(),
-            Self::DataBlockedFrame(_) => f.debug_tuple("DataBlockedFrame").finish(),
-            Self::StreamDataBlockedFrame(_) => f.debug_tuple("StreamDataBlockedFrame").finish(),
-            Self::StreamsBlockedFrame(_) => f.debug_tuple("StreamsBlockedFrame").finish(),
-            Self::NewConnectionIdFrame(_) => f.debug_tuple("NewConnectionIdFrame").finish(),
-            Self::RetireConnectionIdFrame(_) => f.debug_tuple("RetireConnectionIdFrame").finish(),
-            Self::PathChallengeFrame(_) => f.debug_tuple("PathChallengeFrame").finish(),
-            Self::PathResponseFrame(_) => f.debug_tuple("PathResponseFrame").finish(),
-            Self::ConnectionCloseFrame(_) => f.debug_tuple("ConnectionCloseFrame").finish(),
-            Self::HandshakeDoneFrame(_) => f.debug_tuple("HandshakeDoneFrame").finish(),
-            Self::UnknownFrame(_) => f.debug_tuple("UnknownFrame").finish(),
-            Self::DatagramFrame(_) => f.debug_tuple("DatagramFrame").finish(),
+            Self::PaddingFrame(_) => FrameType::Padding,
+            Self::PingFrame(_) => FrameType::Ping,
+            Self::AckFrame(_) => FrameType::Ack,
+            Self::ResetStreamFrame(_) => FrameType::ResetStream,
+            Self::StopSendingFrame(_) => FrameType::StopSending,
+            Self::CryptoFrame(_) => FrameType::Crypto,
+            Self::NewTokenFrame(_) => FrameType::NewToken,
+            Self::StreamFrame(_) => FrameType::Stream,
+            Self::MaxDataFrame(_) => FrameType::MaxData,
+            Self::MaxStreamDataFrame(_) => FrameType::MaxStreamData,
+            Self::MaxStreamsFrame(_) => FrameType::MaxStreams,
+            Self::DataBlockedFrame(_) => FrameType::DataBlocked,
+            Self::StreamDataBlockedFrame(_) => FrameType::StreamDataBlocked,
+            Self::StreamsBlockedFrame(_) => FrameType::StreamsBlocked,
+            Self::NewConnectionIdFrame(_) => FrameType::NewConnectionId,
+            Self::RetireConnectionIdFrame(_) => FrameType::RetireConnectionId,
+            Self::PathChallengeFrame(_) => FrameType::PathChallenge,
+            Self::PathResponseFrame(_) => FrameType::PathResponse,
+            Self::ConnectionCloseFrame(_) => FrameType::ConnectionClose,
+            Self::HandshakeDoneFrame(_) => FrameType::HandshakeDone,
+            Self::UnknownFrame(_) => FrameType::Unknown,
+            Self::DatagramFrame(_) => FrameType::Datagram,
+            Self::ResetStreamAtFrame(_) => FrameType::ResetStreamAt,
         }
     }
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum FrameType {
     Padding,
@@ -383,12 +630,14 @@ pub enum FrameType {
     ConnectionClose,
     HandshakeDone,
     Unknown,
-    Datagram
+    Datagram,
+    ResetStreamAt
 }
 
 /// In QUIC, PADDING frames are simply identified as a single byte of value 0. As such, each padding byte could be theoretically interpreted and logged as an individual PaddingFrame.However, as this leads to heavy logging overhead, implementations should instead emit just a single PaddingFrame and set the raw.payload_length property to the amount of PADDING bytes/frames included in the packet.
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct PaddingFrame {
     frame_type: FrameType,
     raw: Option<RawInfo>
@@ -401,7 +650,8 @@ impl PaddingFrame {
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct PingFrame {
     frame_type: FrameType,
     raw: Option<RawInfo>
@@ -416,11 +666,13 @@ impl PingFrame {
 type AckRange = Vec<u64>;
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct AckFrame {
     frame_type: FrameType,
 
     /// In ms
+    #[serde(serialize_with = "serialize_finite_f32")]
     ack_delay: Option<f32>,
 
     // e.g., looks like [[1,2],[4,5], [7], [10,22]] serialized
@@ -437,10 +689,29 @@ impl AckFrame {
     pub fn new(ack_delay: Option<f32>, acked_ranges: Option<Vec<AckRange>>, ect1: Option<u64>, ect0: Option<u64>, ce: Option<u64>, raw: Option<RawInfo>) -> Self {
         Self { frame_type: FrameType::Ack, ack_delay, acked_ranges, ect1, ect0, ce, raw }
     }
+
+    /// Decodes the ACK Delay field as sent on the wire (RFC 9000 Section 19.3)
+    ///
+    /// Exponents above the maximum of 20 are reported and clamped to 20
+    pub fn with_raw_delay(raw_delay: u64, ack_delay_exponent: u16, acked_ranges: Option<Vec<AckRange>>, ect1: Option<u64>, ect0: Option<u64>, ce: Option<u64>, raw: Option<RawInfo>) -> Self {
+        // RFC 9000 Section 18.2
+        let ack_delay_exponent = if ack_delay_exponent > 20 {
+            diagnostic::report(format_args!("The ack_delay_exponent can't be larger than 20, got {ack_delay_exponent}, using 20 instead"));
+            20
+        }
+        else {
+            ack_delay_exponent
+        };
+
+        let ack_delay_micros = raw_delay as f64 * f64::from(1u32 << ack_delay_exponent);
+
+        Self::new(Some((ack_delay_micros / 1000.0) as f32), acked_ranges, ect1, ect0, ce, raw)
+    }
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ResetStreamFrame {
     frame_type: FrameType,
     stream_id: u64,
@@ -455,16 +726,73 @@ pub struct ResetStreamFrame {
 
 impl ResetStreamFrame {
     pub fn new(stream_id: u64, error_code: ApplicationError, error_code_bytes: Option<u64>, final_size: u64, raw: Option<RawInfo>) -> Self {
+        debug_assert_varint("stream_id", stream_id);
+        debug_assert_varint("final_size", final_size);
+
         if error_code == ApplicationError::Unknown && error_code_bytes.is_none() {
             panic!("When the error_code is 'unknown', provide a value for error_code_bytes");
         }
 
         Self { frame_type: FrameType::ResetStream, stream_id, error_code, error_code_bytes, final_size, raw }
     }
+
+    /// Logs a raw application error code in error_code_bytes
+    pub fn with_code(stream_id: u64, code: u64, final_size: u64, raw: Option<RawInfo>) -> Self {
+        Self::new(stream_id, ApplicationError::Unknown, Some(code), final_size, raw)
+    }
+
+    /// Logs the HTTP/3 error code of the WebTransport error
+    pub fn with_webtransport_error(stream_id: u64, error: WebTransportError, final_size: u64, raw: Option<RawInfo>) -> Self {
+        Self::with_code(stream_id, error.http3_code(), final_size, raw)
+    }
 }
 
+/// RESET_STREAM_AT frame of the reliable stream reset extension (draft-ietf-quic-reliable-stream-reset)
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ResetStreamAtFrame {
+    frame_type: FrameType,
+    stream_id: u64,
+    error_code: ApplicationError,
+
+    error_code_bytes: Option<u64>,
+
+    /// In bytes
+    final_size: u64,
+
+    /// In bytes, the stream data up to this offset is still delivered
+    reliable_size: u64,
+    raw: Option<RawInfo>
+}
+
+impl ResetStreamAtFrame {
+    pub fn new(stream_id: u64, error_code: ApplicationError, error_code_bytes: Option<u64>, final_size: u64, reliable_size: u64, raw: Option<RawInfo>) -> Self {
+        debug_assert_varint("stream_id", stream_id);
+        debug_assert_varint("final_size", final_size);
+        debug_assert_varint("reliable_size", reliable_size);
+
+        if error_code == ApplicationError::Unknown && error_code_bytes.is_none() {
+            panic!("When the error_code is 'unknown', provide a value for error_code_bytes");
+        }
+
+        // Sent by the peer, so a malformed frame is reported but still logged
+        if reliable_size > final_size {
+            diagnostic::report(format_args!("The reliable_size ({reliable_size}) of stream {stream_id} is larger than the final_size ({final_size})"));
+        }
+
+        Self { frame_type: FrameType::ResetStreamAt, stream_id, error_code, error_code_bytes, final_size, reliable_size, raw }
+    }
+
+    /// Logs a raw application error code in error_code_bytes
+    pub fn with_code(stream_id: u64, code: u64, final_size: u64, reliable_size: u64, raw: Option<RawInfo>) -> Self {
+        Self::new(stream_id, ApplicationError::Unknown, Some(code), final_size, reliable_size, raw)
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct StopSendingFrame {
     frame_type: FrameType,
     stream_id: u64,
@@ -477,16 +805,29 @@ pub struct StopSendingFrame {
 
 impl StopSendingFrame {
     pub fn new(stream_id: u64, error_code: ApplicationError, error_code_bytes: Option<u64>, raw: Option<RawInfo>) -> Self {
+        debug_assert_varint("stream_id", stream_id);
+
         if error_code == ApplicationError::Unknown && error_code_bytes.is_none() {
             panic!("When the error_code is 'unknown', give error_code_bytes a value");
         }
 
         Self { frame_type: FrameType::StopSending, stream_id, error_code, error_code_bytes, raw }
     }
+
+    /// Logs a raw application error code in error_code_bytes
+    pub fn with_code(stream_id: u64, code: u64, raw: Option<RawInfo>) -> Self {
+        Self::new(stream_id, ApplicationError::Unknown, Some(code), raw)
+    }
+
+    /// Logs the HTTP/3 error code of the WebTransport error
+    pub fn with_webtransport_error(stream_id: u64, error: WebTransportError, raw: Option<RawInfo>) -> Self {
+        Self::with_code(stream_id, error.http3_code(), raw)
+    }
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct CryptoFrame {
     frame_type: FrameType,
     offset: u64,
@@ -496,12 +837,16 @@ pub struct CryptoFrame {
 
 impl CryptoFrame {
     pub fn new(offset: u64, length: u64, raw: Option<RawInfo>) -> Self {
+        debug_assert_varint("offset", offset);
+        debug_assert_varint("length", length);
+
         Self { frame_type: FrameType::Crypto, offset, length, raw }
     }
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct NewTokenFrame {
     frame_type: FrameType,
     token: Token,
@@ -515,7 +860,8 @@ impl NewTokenFrame {
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct StreamFrame {
     frame_type: FrameType,
     stream_id: u64,
@@ -533,6 +879,10 @@ pub struct StreamFrame {
 
 impl StreamFrame {
     pub fn new(stream_id: u64, offset: u64, length: u64, fin: Option<bool>, raw: Option<RawInfo>) -> Self {
+        debug_assert_varint("stream_id", stream_id);
+        debug_assert_varint("offset", offset);
+        debug_assert_varint("length", length);
+
         let fin = fin.unwrap_or_else(|| false);
 
         Self { frame_type: FrameType::Stream, stream_id, offset, length, fin, raw }
@@ -540,7 +890,8 @@ impl StreamFrame {
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct MaxDataFrame {
     frame_type: FrameType,
     maximum: u64,
@@ -549,12 +900,15 @@ pub struct MaxDataFrame {
 
 impl MaxDataFrame {
     pub fn new(maximum: u64, raw: Option<RawInfo>) -> Self {
+        debug_assert_varint("maximum", maximum);
+
         Self { frame_type: FrameType::MaxData, maximum, raw }
     }
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct MaxStreamDataFrame {
     frame_type: FrameType,
     stream_id: u64,
@@ -564,12 +918,16 @@ pub struct MaxStreamDataFrame {
 
 impl MaxStreamDataFrame {
     pub fn new(stream_id: u64, maximum: u64, raw: Option<RawInfo>) -> Self {
+        debug_assert_varint("stream_id", stream_id);
+        debug_assert_varint("maximum", maximum);
+
         Self { frame_type: FrameType::MaxStreamData, stream_id, maximum, raw }
     }
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct MaxStreamsFrame {
     frame_type: FrameType,
     stream_type: StreamType,
@@ -579,12 +937,15 @@ pub struct MaxStreamsFrame {
 
 impl MaxStreamsFrame {
     pub fn new(stream_type: StreamType, maximum: u64, raw: Option<RawInfo>) -> Self {
+        debug_assert_varint("maximum", maximum);
+
         Self { frame_type: FrameType::MaxStreams, stream_type, maximum, raw }
     }
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct DataBlockedFrame {
     frame_type: FrameType,
     limit: u64,
@@ -593,12 +954,15 @@ pub struct DataBlockedFrame {
 
 impl DataBlockedFrame {
     pub fn new(limit: u64, raw: Option<RawInfo>) -> Self {
+        debug_assert_varint("limit", limit);
+
         Self { frame_type: FrameType::DataBlocked, limit, raw }
     }
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct StreamDataBlockedFrame {
     frame_type: FrameType,
     stream_id: u64,
@@ -608,12 +972,16 @@ pub struct StreamDataBlockedFrame {
 
 impl StreamDataBlockedFrame {
     pub fn new(stream_id: u64, limit: u64, raw: Option<RawInfo>) -> Self {
+        debug_assert_varint("stream_id", stream_id);
+        debug_assert_varint("limit", limit);
+
         Self { frame_type: FrameType::StreamDataBlocked, stream_id, limit, raw }
     }
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct StreamsBlockedFrame {
     frame_type: FrameType,
     stream_type: StreamType,
@@ -623,12 +991,15 @@ pub struct StreamsBlockedFrame {
 
 impl StreamsBlockedFrame {
     pub fn new(stream_type: StreamType, limit: u64, raw: Option<RawInfo>) -> Self {
+        debug_assert_varint("limit", limit);
+
         Self { frame_type: FrameType::StreamsBlocked, stream_type, limit, raw }
     }
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct NewConnectionIdFrame {
     frame_type: FrameType,
     sequence_number: u32,
@@ -645,10 +1016,23 @@ impl NewConnectionIdFrame {
     pub fn new(sequence_number: u32, retire_prior_to: u32, connection_id_length: Option<u8>, connection_id: ConnectionId, stateless_reset_token: Option<StatelessResetToken>, raw: Option<RawInfo>) -> Self {
         Self { frame_type: FrameType::NewConnectionId, sequence_number, retire_prior_to, connection_id_length, connection_id, stateless_reset_token, raw }
     }
+
+    /// Hex-encodes the connection ID and stateless reset token and sets `connection_id_length`
+    pub fn from_bytes(sequence_number: u32, retire_prior_to: u32, connection_id: &[u8], stateless_reset_token: Option<&[u8; StatelessResetToken::LEN]>) -> Self {
+        Self::new(
+            sequence_number,
+            retire_prior_to,
+            connection_id_length(connection_id),
+            bytes_to_hexstring(connection_id),
+            stateless_reset_token.map(StatelessResetToken::from_bytes),
+            None
+        )
+    }
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct RetireConnectionIdFrame {
     frame_type: FrameType,
     sequence_number: u32,
@@ -661,8 +1045,12 @@ impl RetireConnectionIdFrame {
     }
 }
 
+/// Size of the data of PATH_CHALLENGE and PATH_RESPONSE frames in bytes
+pub const PATH_DATA_LEN: usize = 8;
+
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct PathChallengeFrame {
     frame_type: FrameType,
 
@@ -675,10 +1063,19 @@ impl PathChallengeFrame {
     pub fn new(data: Option<HexString>, raw: Option<RawInfo>) -> Self {
         Self { frame_type: FrameType::PathChallenge, data, raw }
     }
+
+    pub fn from_bytes(data: &[u8; PATH_DATA_LEN], raw: Option<RawInfo>) -> Self {
+        Self::new(Some(bytes_to_hexstring(data)), raw)
+    }
+
+    pub fn get_data(&self) -> Option<&HexString> {
+        self.data.as_ref()
+    }
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct PathResponseFrame {
     frame_type: FrameType,
 
@@ -691,9 +1088,18 @@ impl PathResponseFrame {
     pub fn new(data: Option<HexString>, raw: Option<RawInfo>) -> Self {
         Self { frame_type: FrameType::PathResponse, data, raw }
     }
+
+    pub fn from_bytes(data: &[u8; PATH_DATA_LEN], raw: Option<RawInfo>) -> Self {
+        Self::new(Some(bytes_to_hexstring(data)), raw)
+    }
+
+    pub fn get_data(&self) -> Option<&HexString> {
+        self.data.as_ref()
+    }
 }
 
-#[derive(PartialEq, Eq, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum ErrorSpace {
     Transport,
@@ -701,7 +1107,8 @@ pub enum ErrorSpace {
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ConnectionCloseFrame {
     frame_type: FrameType,
     error_space: Option<ErrorSpace>,
@@ -736,9 +1143,48 @@ impl ConnectionCloseFrame {
 
         Self { frame_type: FrameType::ConnectionClose, error_space, error_code, error_code_bytes, reason, reason_bytes, trigger_frame_type, raw }
     }
+
+    /// Sets both the reason and the reason_bytes from the reason phrase on the wire
+    pub fn from_reason_bytes(
+        error_space: Option<ErrorSpace>,
+        error_code: Option<Error>,
+        error_code_bytes: Option<u64>,
+        reason: &[u8],
+        trigger_frame_type: Option<TriggerFrameType>,
+        raw: Option<RawInfo>
+    ) -> Self {
+        Self::new(
+            error_space,
+            error_code,
+            error_code_bytes,
+            Some(String::from_utf8_lossy(reason).into_owned()),
+            Some(bytes_to_hexstring(reason)),
+            trigger_frame_type,
+            raw
+        )
+    }
+
+    /// Logs an application close with a raw application error code in error_code_bytes
+    pub fn with_application_code(code: u64, reason: Option<String>, raw: Option<RawInfo>) -> Self {
+        Self::new(
+            Some(ErrorSpace::Application),
+            Some(Error::ApplicationError(ApplicationError::Unknown)),
+            Some(code),
+            reason,
+            None,
+            None,
+            raw
+        )
+    }
+
+    /// Logs an application close with the HTTP/3 error code of the WebTransport error
+    pub fn with_webtransport_error(error: WebTransportError, reason: Option<String>, raw: Option<RawInfo>) -> Self {
+        Self::with_application_code(error.http3_code(), reason, raw)
+    }
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 pub enum TriggerFrameType {
     U64(u64),
@@ -746,7 +1192,8 @@ pub enum TriggerFrameType {
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct HandshakeDoneFrame {
     frame_type: FrameType,
     raw: Option<RawInfo>
@@ -759,7 +1206,8 @@ impl HandshakeDoneFrame {
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct UnknownFrame {
     frame_type: FrameType,
     frame_type_bytes: u64,
@@ -770,10 +1218,16 @@ impl UnknownFrame {
     pub fn new(frame_type_bytes: u64, raw: Option<RawInfo>) -> Self {
         Self { frame_type: FrameType::Unknown, frame_type_bytes, raw }
     }
+
+    /// For a frame (e.g., a GREASE frame) as a parser encounters it, with the bytes after the frame type in `raw`
+    pub fn from_wire(frame_type: u64, payload: &[u8]) -> Self {
+        Self::new(frame_type, Some(RawInfo::new(Some(payload.len() as u64), Some(payload))))
+    }
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct DatagramFrame {
     frame_type: FrameType,
     length: Option<u64>,
@@ -786,14 +1240,16 @@ impl DatagramFrame {
     }
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum StreamType {
     Unidirectional,
     Bidirectional
 }
 
-#[derive(PartialEq, Eq, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum TransportError {
     NoError,
@@ -816,23 +1272,83 @@ pub enum TransportError {
     Unknown
 }
 
-#[derive(PartialEq, Eq, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum ApplicationError {
     Unknown
 }
 
+/// Errors of WebTransport over HTTP/3, these are carried as HTTP/3 error codes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WebTransportError {
+    BufferedStreamRejected,
+    SessionGone,
+    FlowControlError,
+    AlpnError,
+    RequirementsNotMet,
+    /// A 32-bit WebTransport application error code, mapped into the HTTP/3 error space
+    Application(u32)
+}
+
+impl WebTransportError {
+    /// The HTTP/3 error code of application error code 0
+    pub const FIRST_APPLICATION_CODE: u64 = 0x52e4a40fa8db;
+    /// The HTTP/3 error code of application error code 0xffffffff
+    pub const LAST_APPLICATION_CODE: u64 = 0x52e5ac983162;
+
+    pub fn http3_code(&self) -> u64 {
+        match self {
+            Self::BufferedStreamRejected => 0x3994bd84,
+            Self::SessionGone => 0x170d7b68,
+            Self::FlowControlError => 0x045d4487,
+            Self::AlpnError => 0x0817b3dd,
+            Self::RequirementsNotMet => 0x212c0d48,
+            Self::Application(code) => {
+                let code = u64::from(*code);
+
+                // Skips the reserved (GREASE) HTTP/3 error codes, one in every 0x1f
+                Self::FIRST_APPLICATION_CODE + code + code / 0x1e
+            }
+        }
+    }
+
+    /// The WebTransport error of an HTTP/3 error code, None for other HTTP/3 errors and reserved codes
+    pub fn from_http3_code(code: u64) -> Option<Self> {
+        match code {
+            0x3994bd84 => Some(Self::BufferedStreamRejected),
+            0x170d7b68 => Some(Self::SessionGone),
+            0x045d4487 => Some(Self::FlowControlError),
+            0x0817b3dd => Some(Self::AlpnError),
+            0x212c0d48 => Some(Self::RequirementsNotMet),
+            Self::FIRST_APPLICATION_CODE..=Self::LAST_APPLICATION_CODE => {
+                if (code - 0x21).is_multiple_of(0x1f) {
+                    return None;
+                }
+
+                let shifted = code - Self::FIRST_APPLICATION_CODE;
+
+                // Within the range, so the result always fits
+                Some(Self::Application((shifted - shifted / 0x1f) as u32))
+            },
+            _ => None
+        }
+    }
+}
+
 /// All strings from "crypto_error_0x100" to "crypto_error_0x1ff".
 pub type CryptoError = String;
 
-#[derive(PartialEq, Eq, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 pub enum ConnectionError {
     TransportError(TransportError),
     CryptoError(CryptoError)
 }
 
-#[derive(PartialEq, Eq, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 pub enum Error {
     TransportError(TransportError),
@@ -840,14 +1356,39 @@ pub enum Error {
     ApplicationError(ApplicationError)
 }
 
-#[derive(Serialize)]
+impl Error {
+    pub fn transport(error: TransportError) -> Self {
+        Self::TransportError(error)
+    }
+
+    /// From the TLS alert, which is carried as error code 0x100 + alert
+    pub fn crypto(alert: u8) -> Self {
+        Self::CryptoError(format!("crypto_error_0x1{alert:02x}"))
+    }
+}
+
+impl From<TransportError> for Error {
+    fn from(value: TransportError) -> Self {
+        Self::TransportError(value)
+    }
+}
+
+impl From<ApplicationError> for Error {
+    fn from(value: ApplicationError) -> Self {
+        Self::ApplicationError(value)
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 pub enum ConnectionState {
     BaseConnectionState(BaseConnectionState),
     GranularConnectionState(GranularConnectionState)
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum BaseConnectionState {
     /// Initial packet sent/received.
@@ -865,7 +1406,8 @@ pub enum BaseConnectionState {
     Closed
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum GranularConnectionState {
     /// Client sent Handshake packet OR 
@@ -893,14 +1435,68 @@ pub enum GranularConnectionState {
     Closed
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(untagged)]
 pub enum StreamState {
     BaseStreamState(BaseStreamState),
     GranularStreamState(GranularStreamState)
 }
 
-#[derive(Serialize)]
+impl StreamState {
+    /// No further transitions are possible, except for the memory of the stream being freed (Destroyed)
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            Self::BaseStreamState(BaseStreamState::Closed)
+                | Self::GranularStreamState(GranularStreamState::DataRead | GranularStreamState::ResetRead | GranularStreamState::Destroyed)
+        )
+    }
+
+    /// Checks a transition against the stream state machines of RFC 9000 Section 3, including the composite states of mixed base and granular states
+    pub fn can_transition_to(&self, new: &StreamState) -> bool {
+        use BaseStreamState as B;
+        use GranularStreamState as G;
+
+        if *new == Self::GranularStreamState(G::Destroyed) {
+            return *self != Self::GranularStreamState(G::Destroyed);
+        }
+
+        match (self, new) {
+            (Self::BaseStreamState(old), Self::BaseStreamState(new)) => matches!(
+                (old, new),
+                (B::Idle, B::Open) | (B::Idle, B::Closed) | (B::Open, B::Closed)
+            ),
+            (Self::GranularStreamState(old), Self::GranularStreamState(new)) => matches!(
+                (old, new),
+                // Sending side
+                (G::Ready, G::Send) | (G::Ready, G::ResetSent)
+                | (G::Send, G::DataSent) | (G::Send, G::ResetSent)
+                | (G::DataSent, G::DataReceived) | (G::DataSent, G::ResetSent)
+                | (G::ResetSent, G::ResetReceived)
+                // Receiving side
+                | (G::Receive, G::SizeKnown) | (G::Receive, G::ResetReceived)
+                | (G::SizeKnown, G::DataReceived) | (G::SizeKnown, G::ResetReceived)
+                // A RESET_STREAM can still arrive after all data was received (RFC 9000 Section 3.2)
+                | (G::DataReceived, G::DataRead) | (G::DataReceived, G::ResetReceived)
+                | (G::ResetReceived, G::ResetRead)
+            ),
+            (Self::BaseStreamState(old), Self::GranularStreamState(new)) => matches!(
+                (old, new),
+                (B::Idle, G::Ready | G::Receive | G::HalfClosedLocal | G::HalfClosedRemote)
+                | (B::Open, G::HalfClosedLocal | G::HalfClosedRemote)
+            ),
+            (Self::GranularStreamState(old), Self::BaseStreamState(new)) => matches!(
+                (old, new),
+                (G::Ready | G::Receive, B::Open)
+                | (G::HalfClosedLocal | G::HalfClosedRemote, B::Closed)
+            )
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum BaseStreamState {
     Idle,
@@ -908,7 +1504,8 @@ pub enum BaseStreamState {
     Closed
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum GranularStreamState {
     // Bidirectional stream states, RFC 9000 Section 3.4.
@@ -935,7 +1532,8 @@ pub enum GranularStreamState {
     Destroyed
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum StreamSide {
     Sending,
@@ -943,7 +1541,8 @@ pub enum StreamSide {
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct AlpnIdentifier {
     byte_value: Option<HexString>,
     string_value: Option<String>
@@ -956,7 +1555,8 @@ impl AlpnIdentifier {
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct PreferredAddress {
     ip_v4: Option<IpAddress>,
     port_v4: Option<u16>,
@@ -973,7 +1573,8 @@ impl PreferredAddress {
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct UnknownParameter {
     id: u64,
     value: Option<HexString>
@@ -983,9 +1584,19 @@ impl UnknownParameter {
     pub fn new(id: u64, value: Option<HexString>) -> Self {
         Self { id, value }
     }
+
+    /// Only the first MAX_LOG_DATA_LEN bytes of the value are logged
+    pub fn from_bytes(id: u64, value: &[u8]) -> Self {
+        Self::new(id, Some(bytes_to_truncated_hexstring(value)))
+    }
+
+    pub fn from_bytes_iter<'a>(parameters: impl IntoIterator<Item = (u64, &'a [u8])>) -> Vec<Self> {
+        parameters.into_iter().map(|(id, value)| Self::from_bytes(id, value)).collect()
+    }
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum ConnectionCloseTrigger {
     IdleTimeout,
@@ -999,7 +1610,8 @@ pub enum ConnectionCloseTrigger {
     Unspecified
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum PacketSentTrigger {
     // RFC 9002 Section 6.1.1
@@ -1014,14 +1626,20 @@ pub enum PacketSentTrigger {
     CcBandwidthProbe
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum PacketReceivedTrigger {
     // If packet was buffered because it couldn't be decrypted before
-    KeysAvailable
+    KeysAvailable,
+    // If packet was buffered because it arrived out of order (e.g., ahead of a missing datagram)
+    DatagramReordered,
+    // If packet was buffered because of backpressure (see PacketBufferedTrigger::Backpressure)
+    Unbuffered
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum PacketDroppedTrigger {
     InternalError,
@@ -1035,7 +1653,8 @@ pub enum PacketDroppedTrigger {
     General
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum PacketBufferedTrigger {
     /// Indicates the parser cannot keep up, temporarily buffers packet for later processing
@@ -1044,7 +1663,8 @@ pub enum PacketBufferedTrigger {
     KeysUnavailable
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum KeyUpdateTrigger {
     // (e.g., initial, handshake and 0-RTT keys are generated by TLS)
@@ -1053,7 +1673,8 @@ pub enum KeyUpdateTrigger {
     LocalUpdate
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum KeyDiscardTrigger {
     // (e.g., initial, handshake and 0-RTT keys are generated by TLS)
@@ -1062,7 +1683,18 @@ pub enum KeyDiscardTrigger {
     LocalUpdate
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum KeyRetireTrigger {
+    // (e.g., handshake keys are no longer used once 1-RTT keys are available)
+    Tls,
+    RemoteUpdate,
+    LocalUpdate
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum PacketLostTrigger {
     ReorderingThreshold,
@@ -1071,7 +1703,8 @@ pub enum PacketLostTrigger {
     PtoExpired
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum DataLocation {
     Application,
@@ -1079,7 +1712,21 @@ pub enum DataLocation {
     Network
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum DatagramDroppedReason {
+    /// The data wasn't sent or read in time
+    Expired,
+    /// The send or receive queue was full
+    QueueFull,
+    /// The data doesn't fit in a single packet (see max_datagram_frame_size)
+    TooLarge,
+    Unknown
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum DataMovedAdditionalInfo {
     FinSet,
@@ -1088,7 +1735,8 @@ pub enum DataMovedAdditionalInfo {
 
 /// Note that MigrationState does not describe a full state machine.
 /// These entries are not necessarily chronological, nor will they always all appear during a connection migration attempt.
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum MigrationState {
     /// Probing packets are sent, migration not initiated yet
@@ -1105,14 +1753,33 @@ pub enum MigrationState {
     MigrationComplete
 }
 
-#[derive(Serialize)]
+/// Why a migration state was entered, mainly useful for ProbingAbandoned and MigrationAbandoned
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationTrigger {
+    /// No reply to the probing packets in time (e.g., a black hole)
+    ProbeTimeout,
+    /// An ICMP Packet Too Big message was received for the path
+    IcmpPtb,
+    /// Path validation failed (e.g., PATH_RESPONSE didn't match)
+    ValidationFailed,
+    /// Migrating to the server's preferred address
+    PreferredAddress,
+    #[serde(untagged)]
+    Other(String)
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum TimerType {
     Ack,
     Pto
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum EventType {
     Set,
@@ -1120,7 +1787,8 @@ pub enum EventType {
     Cancelled
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum EcnState {
     /// ECN testing in progress
@@ -1132,3 +1800,88 @@ pub enum EcnState {
     /// Testing was successful, the endpoint now sends packets with ECT(0) marking
     Capable
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{diagnostic, util::bytes_to_hexstring};
+
+    use super::{BaseStreamState, ConnectionCloseFrame, GranularStreamState, PacketHeader, PacketType, StreamState};
+
+    #[test]
+    fn mismatched_connection_id_lengths_are_reported() {
+        let (header, reports) = diagnostic::capture(|| {
+            PacketHeader::new(None, PacketType::Handshake, None, Some(0), None, None, Some(0), None, Some(4), Some(2), Some(bytes_to_hexstring(&[1, 2])), Some(bytes_to_hexstring(&[3, 4])))
+        });
+
+        assert_eq!(reports.len(), 1);
+
+        let json = serde_json::to_value(header).unwrap();
+
+        assert_eq!(json["scil"], 4);
+        assert_eq!(json["scid"], "0102");
+    }
+
+    #[test]
+    fn long_header_lengths_are_derived() {
+        let (header, reports) = diagnostic::capture(|| {
+            PacketHeader::long_header(None, PacketType::Handshake, None, Some(0), None, None, Some(0), None, &[1, 2, 3], &[0; 256])
+        });
+
+        assert_eq!(reports.len(), 1);
+
+        let json = serde_json::to_value(header).unwrap();
+
+        assert_eq!(json["scil"], 3);
+        assert!(json.get("dcil").is_none());
+    }
+
+    #[test]
+    fn stream_state_transitions() {
+        use BaseStreamState as B;
+        use GranularStreamState as G;
+
+        let base = StreamState::BaseStreamState;
+        let granular = StreamState::GranularStreamState;
+
+        let legal = [
+            (base(B::Idle), base(B::Open)),
+            (base(B::Open), granular(G::HalfClosedLocal)),
+            (granular(G::HalfClosedLocal), base(B::Closed)),
+            (granular(G::HalfClosedRemote), base(B::Closed)),
+            (base(B::Idle), granular(G::Ready)),
+            (granular(G::Receive), base(B::Open)),
+            (granular(G::DataReceived), granular(G::ResetReceived)),
+            (granular(G::ResetReceived), granular(G::ResetRead)),
+            (base(B::Closed), granular(G::Destroyed))
+        ];
+
+        for (old, new) in legal {
+            assert!(old.can_transition_to(&new), "{old:?} -> {new:?} should be legal");
+        }
+
+        let illegal = [
+            (base(B::Closed), base(B::Open)),
+            (granular(G::HalfClosedLocal), granular(G::HalfClosedRemote)),
+            (granular(G::HalfClosedRemote), granular(G::HalfClosedLocal)),
+            (granular(G::Send), base(B::Idle)),
+            (granular(G::DataSent), base(B::Open)),
+            (base(B::Open), granular(G::Ready)),
+            (granular(G::DataRead), granular(G::Receive)),
+            (granular(G::Destroyed), granular(G::Destroyed))
+        ];
+
+        for (old, new) in illegal {
+            assert!(!old.can_transition_to(&new), "{old:?} -> {new:?} should be illegal");
+        }
+    }
+
+    #[test]
+    fn application_close_carries_the_code() {
+        let frame = ConnectionCloseFrame::with_application_code(0x10c, Some("done".to_string()), None);
+        let json = serde_json::to_value(frame).unwrap();
+
+        assert_eq!(json["error_space"], "application");
+        assert_eq!(json["error_code"], "unknown");
+        assert_eq!(json["error_code_bytes"], 0x10c);
+    }
+}
@@ -1,16 +1,17 @@
-use std::collections::HashMap;
+use std::{collections::BTreeMap, time::Duration};
 
 use serde::Serialize;
 use serde_with::skip_serializing_none;
 
-use crate::{events::RawInfo, util::{HexString, PathId}};
+use crate::{diagnostic, events::{EventTime, PathId, RawInfo}, util::{bytes_to_hexstring, serialize_finite_f32, HexString}};
 
 use super::data::*;
 
 // Values are optional because some QUIC stacks do not handle sockets directly and are thus unable to log IP and/or port information
 /// Emitted when the server starts accepting connections.
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ServerListening {
     ip_v4: Option<IpAddress>,
     port_v4: Option<u16>,
@@ -27,7 +28,8 @@ impl ServerListening {
 }
 
 /// Used for both attempting (client-perspective) and accepting (server-perspective) new connections.
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ConnectionStarted {
     local: PathEndpointInfo,
     remote: PathEndpointInfo
@@ -45,7 +47,8 @@ impl ConnectionStarted {
 /// or when a Stateless Reset packet is received (the connection is discarded at the receiver side). 
 /// Connectivity-related updates after this point (e.g., exiting a 'closing' or 'draining' state), should be logged using the ConnectionStateUpdated event instead.
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ConnectionClosed {
     /// Which side closed the connection
     owner: Option<Owner>,
@@ -79,7 +82,8 @@ impl ConnectionClosed {
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ConnectionIdUpdated {
     /// When the endpoint receives a new connection ID from the peer, this will be Remote.
     /// When the endpoint updates its own connection ID, this will be Local.
@@ -95,7 +99,8 @@ impl ConnectionIdUpdated {
 }
 
 /// Emitted when the spin bit changes value, should not be emitted if the spin bit is set without changing its value.
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct SpinBitUpdated {
     state: bool
 }
@@ -108,7 +113,8 @@ impl SpinBitUpdated {
 
 /// QUIC implementations should mainly log the simplified BaseConnectionStates, adding the more fine-grained GranularConnectionStates when more in-depth debugging is required. Tools should be able to deal with both types equally.
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ConnectionStateUpdated {
     old: Option<ConnectionState>,
     new: ConnectionState
@@ -121,7 +127,8 @@ impl ConnectionStateUpdated {
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct PathAssigned {
     path_id: PathId,
 
@@ -138,8 +145,49 @@ impl PathAssigned {
     }
 }
 
+/// Emitted when a (multipath) path changes after it was assigned (e.g., after its validation).
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct PathUpdated {
+    path_id: PathId,
+
+    /// Information for traffic going towards the remote receiver.
+    path_remote: Option<PathEndpointInfo>,
+
+    /// Information for traffic coming in at the local endpoint.
+    path_local: Option<PathEndpointInfo>,
+
+    reason: Option<String>
+}
+
+impl PathUpdated {
+    pub fn new(path_id: PathId, path_remote: Option<PathEndpointInfo>, path_local: Option<PathEndpointInfo>, reason: Option<String>) -> Self {
+        Self { path_id, path_remote, path_local, reason }
+    }
+}
+
+/// Emitted when a (multipath) path is abandoned and no longer used
+#[skip_serializing_none]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct PathAbandoned {
+    path_id: PathId,
+
+    /// Error code of the PATH_ABANDON frame
+    error_code: Option<u64>,
+    reason: Option<String>
+}
+
+impl PathAbandoned {
+    pub fn new(path_id: PathId, error_code: Option<u64>, reason: Option<String>) -> Self {
+        Self { path_id, error_code, reason }
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct MtuUpdated {
     old: Option<u32>,
     new: u32,
@@ -161,7 +209,8 @@ impl MtuUpdated {
 ///   - Upon receiving a client initial with a supported version, the server logs this event with server_versions and chosen_version setUpon receiving a client initial with an unsupported version, the server logs this event with server_versions set and client_versions to the single-element array containing the client's attempted version. The absence of chosen_version implies no overlap was found
 ///   - Upon receiving a version negotiation packet from the server, the client logs this event with client_versions set and server_versions to the versions in the version negotiation packet and chosen_version to the version it will use for the next initial packet. If the client receives a set of server_versions with no viable overlap with its own supported versions, this event should be logged without the chosen_version set
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct VersionInformation {
     server_versions: Option<Vec<QuicVersion>>,
     client_versions: Option<Vec<QuicVersion>>,
@@ -180,7 +229,8 @@ impl VersionInformation {
 ///   - When receiving an initial with an alpn, the client logs this event with chosen_alpn to the received value.
 ///   - Alternatively, a client can choose to not log the first event, but wait for the receipt of the server initial to log this event with both client_alpns and chosen_alpn set.
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct AlpnInformation {
     server_alpns: Option<Vec<AlpnIdentifier>>,
     client_alpns: Option<Vec<AlpnIdentifier>>,
@@ -194,7 +244,8 @@ impl AlpnInformation {
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ParametersSet {
     owner: Option<Owner>,
 
@@ -205,7 +256,10 @@ pub struct ParametersSet {
     early_data_enabled: Option<bool>,
 
     /// e.g., "AES_128_GCM_SHA256"
-    tls_cipher: Option<String>,
+    tls_cipher: Option<TlsCipher>,
+
+    /// e.g., "x25519"
+    key_exchange_group: Option<KeyExchangeGroup>,
     
     // RFC9000
     original_destination_connection_id: Option<ConnectionId>,
@@ -231,7 +285,7 @@ pub struct ParametersSet {
     max_datagram_frame_size: Option<u64>,
 
     // RFC9287
-    /// True if present, absent or false if extension not negotiated
+    /// None if the extension was not negotiated, otherwise always serialized (including false)
     grease_quic_bit: Option<bool>
 }
 
@@ -240,7 +294,8 @@ impl ParametersSet {
         owner: Option<Owner>,
         resumption_allowed: Option<bool>,
         early_data_enabled: Option<bool>,
-        tls_cipher: Option<String>,
+        tls_cipher: Option<TlsCipher>,
+        key_exchange_group: Option<KeyExchangeGroup>,
         original_destination_connection_id: Option<ConnectionId>,
         initial_source_connection_id: Option<ConnectionId>,
         retry_source_connection_id: Option<ConnectionId>,
@@ -267,6 +322,7 @@ impl ParametersSet {
             resumption_allowed,
             early_data_enabled,
             tls_cipher,
+            key_exchange_group,
             original_destination_connection_id,
             initial_source_connection_id,
             retry_source_connection_id,
@@ -289,10 +345,21 @@ impl ParametersSet {
             grease_quic_bit
         }
     }
+
+    /// Reports inconsistent parameters (e.g., early data without resumption) and returns whether they're consistent
+    pub fn check(&self) -> bool {
+        if self.early_data_enabled == Some(true) && self.resumption_allowed == Some(false) {
+            diagnostic::report(format_args!("When early_data_enabled is true, resumption_allowed can't be false (0-RTT requires resumption)"));
+            return false;
+        }
+
+        true
+    }
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ParametersRestored {
     // RFC9000
     disable_active_migration: Option<bool>,
@@ -311,6 +378,7 @@ pub struct ParametersRestored {
 
     // RFC9287
     /// Can only be restored at the client. Servers must not restore this parameter!
+    /// None if the extension was not negotiated (field is omitted), otherwise the value is always serialized (including false)
     grease_quic_bit: Option<bool>
 }
 
@@ -347,7 +415,8 @@ impl ParametersRestored {
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct PacketSent {
     header: PacketHeader,
     frames: Option<Vec<QuicFrame>>,
@@ -360,9 +429,19 @@ pub struct PacketSent {
     supported_versions: Option<Vec<QuicVersion>>,
     raw: Option<RawInfo>,
     datagram_id: Option<u32>,
+
+    /// ECN codepoint the packet was marked with (lost at the datagram level when packets are coalesced)
+    ecn: Option<Ecn>,
     is_mtu_probe_packet: bool,
 
-    trigger: Option<PacketSentTrigger>
+    trigger: Option<PacketSentTrigger>,
+
+    /// Only if header.packet_type == Retry.
+    /// Always 128 bits in length.
+    retry_integrity_tag: Option<HexString>,
+
+    /// Set when frames were dropped to keep the record under the writer's maximum record size
+    truncated: Option<bool>
 }
 
 impl PacketSent {
@@ -378,7 +457,22 @@ impl PacketSent {
     ) -> Self {
         let is_mtu_probe_packet = is_mtu_probe_packet.unwrap_or_else(|| false);
 
-        Self { header, frames, stateless_reset_token, supported_versions, raw, datagram_id, is_mtu_probe_packet, trigger }
+        debug_assert!(
+            supported_versions.is_none() || *header.get_packet_type() == PacketType::VersionNegotiation,
+            "supported_versions should only be set when the packet_type is 'version_negotiation'"
+        );
+
+        Self { header, frames, stateless_reset_token, supported_versions, raw, datagram_id, ecn: None, is_mtu_probe_packet, trigger, retry_integrity_tag: None, truncated: None }
+    }
+
+    pub fn builder(header: PacketHeader) -> PacketSentBuilder {
+        PacketSentBuilder::new(header)
+    }
+
+    pub fn version_negotiation(header: PacketHeader, supported_versions: Vec<QuicVersion>) -> Self {
+        Self::check_version_negotiation(&header, &supported_versions);
+
+        Self::new(header, None, None, Some(supported_versions), None, None, None, None)
     }
 
     pub fn add_frame(&mut self, frame: QuicFrame) {
@@ -388,13 +482,128 @@ impl PacketSent {
         }
     }
 
+    pub fn set_retry_integrity_tag(&mut self, tag: &[u8]) {
+        if *self.header.get_packet_type() != PacketType::Retry {
+            panic!("Only packets with packet_type 'retry' carry a retry integrity tag");
+        }
+
+        if tag.len() != RETRY_INTEGRITY_TAG_LEN {
+            panic!("The retry integrity tag must be 128 bits long, got {} bytes", tag.len());
+        }
+
+        self.retry_integrity_tag = Some(bytes_to_hexstring(tag));
+    }
+
+    pub fn set_ecn(&mut self, ecn: Ecn) {
+        self.ecn = Some(ecn);
+    }
+
     pub fn update_packet_length(&mut self, payload_length: u16) {
         self.header.update_packet_length(payload_length);
     }
+
+    fn check_version_negotiation(header: &PacketHeader, supported_versions: &[QuicVersion]) {
+        if *header.get_packet_type() != PacketType::VersionNegotiation {
+            panic!("A version negotiation packet needs a header with packet_type 'version_negotiation'");
+        }
+
+        if supported_versions.is_empty() {
+            panic!("A version negotiation packet needs at least one supported version");
+        }
+    }
+
+    /// True if the packet contains at least one ack-eliciting frame
+    pub fn is_ack_eliciting(&self) -> bool {
+        self.frames.as_ref().is_some_and(|frames| frames.iter().any(QuicFrame::is_ack_eliciting))
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.as_ref().map_or(0, Vec::len)
+    }
+
+    pub fn get_datagram_id(&self) -> Option<u32> {
+        self.datagram_id
+    }
+
+    pub fn get_raw(&self) -> Option<&RawInfo> {
+        self.raw.as_ref()
+    }
+
+    /// Keeps only the first `len` frames and marks the packet as truncated
+    pub(crate) fn truncate_frames(&mut self, len: usize) {
+        if let Some(frames) = self.frames.as_mut() {
+            frames.truncate(len);
+            self.truncated = Some(true);
+        }
+    }
+}
+
+/// Assembles a `PacketSent` without going through the writer's packet cache
+///
+/// ```ignore
+/// let packet = PacketSent::builder(header).frame(ping_frame).datagram_id(1).build();
+/// ```
+#[derive(Clone, Debug)]
+pub struct PacketSentBuilder {
+    header: PacketHeader,
+    frames: Option<Vec<QuicFrame>>,
+    raw: Option<RawInfo>,
+    datagram_id: Option<u32>,
+    ecn: Option<Ecn>,
+    is_mtu_probe_packet: bool,
+    trigger: Option<PacketSentTrigger>
+}
+
+impl PacketSentBuilder {
+    fn new(header: PacketHeader) -> Self {
+        Self { header, frames: None, raw: None, datagram_id: None, ecn: None, is_mtu_probe_packet: false, trigger: None }
+    }
+
+    pub fn frame(mut self, frame: QuicFrame) -> Self {
+        self.frames.get_or_insert_with(Vec::new).push(frame);
+        self
+    }
+
+    pub fn frames(mut self, frames: impl IntoIterator<Item = QuicFrame>) -> Self {
+        self.frames.get_or_insert_with(Vec::new).extend(frames);
+        self
+    }
+
+    pub fn raw(mut self, raw: RawInfo) -> Self {
+        self.raw = Some(raw);
+        self
+    }
+
+    pub fn datagram_id(mut self, datagram_id: u32) -> Self {
+        self.datagram_id = Some(datagram_id);
+        self
+    }
+
+    pub fn ecn(mut self, ecn: Ecn) -> Self {
+        self.ecn = Some(ecn);
+        self
+    }
+
+    pub fn mtu_probe(mut self) -> Self {
+        self.is_mtu_probe_packet = true;
+        self
+    }
+
+    pub fn trigger(mut self, trigger: PacketSentTrigger) -> Self {
+        self.trigger = Some(trigger);
+        self
+    }
+
+    pub fn build(self) -> PacketSent {
+        let mut packet = PacketSent::new(self.header, self.frames, None, None, self.raw, self.datagram_id, Some(self.is_mtu_probe_packet), self.trigger);
+        packet.ecn = self.ecn;
+        packet
+    }
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct PacketReceived {
     header: PacketHeader,
     frames: Option<Vec<QuicFrame>>,
@@ -408,7 +617,17 @@ pub struct PacketReceived {
     raw: Option<RawInfo>,
     datagram_id: Option<u32>,
 
-    trigger: Option<PacketReceivedTrigger>
+    /// ECN codepoint the packet was marked with (lost at the datagram level when packets are coalesced)
+    ecn: Option<Ecn>,
+
+    trigger: Option<PacketReceivedTrigger>,
+
+    /// Only if header.packet_type == Retry.
+    /// Always 128 bits in length.
+    retry_integrity_tag: Option<HexString>,
+
+    /// Set when frames were dropped to keep the record under the writer's maximum record size
+    truncated: Option<bool>
 }
 
 impl PacketReceived {
@@ -421,7 +640,22 @@ impl PacketReceived {
         datagram_id: Option<u32>,
         trigger: Option<PacketReceivedTrigger>
     ) -> Self {
-        Self { header, frames, stateless_reset_token, supported_versions, raw, datagram_id, trigger }
+        debug_assert!(
+            supported_versions.is_none() || *header.get_packet_type() == PacketType::VersionNegotiation,
+            "supported_versions should only be set when the packet_type is 'version_negotiation'"
+        );
+
+        Self { header, frames, stateless_reset_token, supported_versions, raw, datagram_id, ecn: None, trigger, retry_integrity_tag: None, truncated: None }
+    }
+
+    pub fn builder(header: PacketHeader) -> PacketReceivedBuilder {
+        PacketReceivedBuilder::new(header)
+    }
+
+    pub fn version_negotiation(header: PacketHeader, supported_versions: Vec<QuicVersion>) -> Self {
+        PacketSent::check_version_negotiation(&header, &supported_versions);
+
+        Self::new(header, None, None, Some(supported_versions), None, None, None)
     }
 
     pub fn add_frame(&mut self, frame: QuicFrame) {
@@ -430,17 +664,100 @@ impl PacketReceived {
             None => self.frames = Some(vec![frame]),
         }
     }
+
+    pub fn set_retry_integrity_tag(&mut self, tag: &[u8]) {
+        if *self.header.get_packet_type() != PacketType::Retry {
+            panic!("Only packets with packet_type 'retry' carry a retry integrity tag");
+        }
+
+        if tag.len() != RETRY_INTEGRITY_TAG_LEN {
+            panic!("The retry integrity tag must be 128 bits long, got {} bytes", tag.len());
+        }
+
+        self.retry_integrity_tag = Some(bytes_to_hexstring(tag));
+    }
+
+    pub fn set_ecn(&mut self, ecn: Ecn) {
+        self.ecn = Some(ecn);
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.as_ref().map_or(0, Vec::len)
+    }
+
+    /// Keeps only the first `len` frames and marks the packet as truncated
+    pub(crate) fn truncate_frames(&mut self, len: usize) {
+        if let Some(frames) = self.frames.as_mut() {
+            frames.truncate(len);
+            self.truncated = Some(true);
+        }
+    }
+}
+
+/// Assembles a `PacketReceived` without going through the writer's packet cache
+#[derive(Clone, Debug)]
+pub struct PacketReceivedBuilder {
+    header: PacketHeader,
+    frames: Option<Vec<QuicFrame>>,
+    raw: Option<RawInfo>,
+    datagram_id: Option<u32>,
+    ecn: Option<Ecn>,
+    trigger: Option<PacketReceivedTrigger>
+}
+
+impl PacketReceivedBuilder {
+    fn new(header: PacketHeader) -> Self {
+        Self { header, frames: None, raw: None, datagram_id: None, ecn: None, trigger: None }
+    }
+
+    pub fn frame(mut self, frame: QuicFrame) -> Self {
+        self.frames.get_or_insert_with(Vec::new).push(frame);
+        self
+    }
+
+    pub fn frames(mut self, frames: impl IntoIterator<Item = QuicFrame>) -> Self {
+        self.frames.get_or_insert_with(Vec::new).extend(frames);
+        self
+    }
+
+    pub fn raw(mut self, raw: RawInfo) -> Self {
+        self.raw = Some(raw);
+        self
+    }
+
+    pub fn datagram_id(mut self, datagram_id: u32) -> Self {
+        self.datagram_id = Some(datagram_id);
+        self
+    }
+
+    pub fn ecn(mut self, ecn: Ecn) -> Self {
+        self.ecn = Some(ecn);
+        self
+    }
+
+    pub fn trigger(mut self, trigger: PacketReceivedTrigger) -> Self {
+        self.trigger = Some(trigger);
+        self
+    }
+
+    pub fn build(self) -> PacketReceived {
+        let mut packet = PacketReceived::new(self.header, self.frames, None, None, self.raw, self.datagram_id, self.trigger);
+        packet.ecn = self.ecn;
+        packet
+    }
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct PacketDropped {
     // Primarily packet_type should be filled here, as other fields might not be decrypteable or parseable
     header: Option<PacketHeader>,
     raw: Option<RawInfo>,
     datagram_id: Option<u32>,
-    #[serde(skip_serializing_if = "HashMap::is_empty")]
-    details: HashMap<String, Vec<u8>>,
+    /// Readable text or hex encoded bytes (see `details_from_bytes()`)
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    details: BTreeMap<String, HexString>,
     trigger: Option<PacketDroppedTrigger>
 }
 
@@ -449,15 +766,38 @@ impl PacketDropped {
         header: Option<PacketHeader>,
         raw: Option<RawInfo>,
         datagram_id: Option<u32>,
-        details: HashMap<String, Vec<u8>>,
+        details: BTreeMap<String, HexString>,
         trigger: Option<PacketDroppedTrigger>
     ) -> Self {
         Self { header, raw, datagram_id, details, trigger }
     }
+
+    /// Hex encodes the values of the details
+    pub fn details_from_bytes(details: BTreeMap<String, Vec<u8>>) -> BTreeMap<String, HexString> {
+        details.into_iter().map(|(name, bytes)| (name, bytes_to_hexstring(&bytes))).collect()
+    }
 }
 
+/// A packet as captured without decoding it (e.g., the encrypted UDP payload)
+///
+/// Unlike PacketDropped, this doesn't imply the packet was discarded
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct RawPacketCaptured {
+    raw: RawInfo,
+    datagram_id: Option<u32>
+}
+
+impl RawPacketCaptured {
+    pub fn new(raw: RawInfo, datagram_id: Option<u32>) -> Self {
+        Self { raw, datagram_id }
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct PacketBuffered {
     // Primarily packet_type and possible packet_number should be filled here as other elements might not be available yet
     header: Option<PacketHeader>,
@@ -474,22 +814,46 @@ impl PacketBuffered {
 
 /// Emitted when a (group of) sent packet(s) is acknowledged by the remote peer for the first time.
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct PacketsAcked {
     packet_number_space: Option<PacketNumberSpace>,
-    packet_numbers: Option<Vec<u64>>
+    packet_numbers: Option<Vec<u64>>,
+
+    /// Send time of the largest acknowledged packet that was cached by the writer
+    largest_acked_sent_time: Option<EventTime>
 }
 
 impl PacketsAcked {
     pub fn new(packet_number_space: Option<PacketNumberSpace>, packet_numbers: Option<Vec<u64>>) -> Self {
-        Self { packet_number_space, packet_numbers }
+        Self { packet_number_space, packet_numbers, largest_acked_sent_time: None }
+    }
+
+    pub(crate) fn set_largest_acked_sent_time(&mut self, time: EventTime) {
+        self.largest_acked_sent_time = Some(time);
+    }
+}
+
+/// Emitted when a packet number is deliberately not used (e.g., to detect optimistic ACKs, RFC 9000 Section 21.4).
+#[skip_serializing_none]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct PacketNumberSkipped {
+    packet_number_space: Option<PacketNumberSpace>,
+    packet_number: u64
+}
+
+impl PacketNumberSkipped {
+    pub fn new(packet_number_space: Option<PacketNumberSpace>, packet_number: u64) -> Self {
+        Self { packet_number_space, packet_number }
     }
 }
 
 /// Emitted when one or more UDP-level datagrams are passed to the underlying network socket.
 /// This is useful for determining how QUIC packet buffers are drained to the OS.
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct UdpDatagramsSent {
     // To support passing multiple at once
     count: Option<u16>,
@@ -513,7 +877,8 @@ impl UdpDatagramsSent {
 /// Emitted when one or more UDP-level datagrams are received from the socket.
 /// This is useful for determining how datagrams are passed to the user space stack from the OS.
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct UdpDatagramsReceived {
     // To support passing multiple at once
     count: Option<u16>,
@@ -538,7 +903,8 @@ impl UdpDatagramsReceived {
 /// This is typically done if it does not contain a valid QUIC packet.
 /// If it does, but the QUIC packet is dropped for other reasons, the PacketDropped event should be used instead.
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct UdpDatagramDropped {
     /// The RawInfo fields do not include the UDP headers, only the UDP payload
     raw: Option<RawInfo>,
@@ -554,7 +920,8 @@ impl UdpDatagramDropped {
 /// QUIC implementations should mainly log the simplified (HTTP/2-alike) BaseStreamStates instead of the more fine-grained GranularStreamStates.
 /// These latter ones are mainly for more in-depth debugging.
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct StreamStateUpdated {
     stream_id: u64,
 
@@ -573,15 +940,56 @@ impl StreamStateUpdated {
 
 /// Intended to prevent a large proliferation of specific purpose events.
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct FramesProcessed {
     frames: Vec<QuicFrame>,
-    packet_numbers: Option<Vec<u64>>
+    packet_numbers: Option<Vec<u64>>,
+
+    /// Application layer frames (e.g., HTTP/3 frames) carried in the processed frames
+    application_frames: Option<Vec<serde_json::Value>>,
+
+    /// Set when frames were dropped to keep the record under the writer's maximum record size
+    truncated: Option<bool>
 }
 
 impl FramesProcessed {
     pub fn new(frames: Vec<QuicFrame>, packet_numbers: Option<Vec<u64>>) -> Self {
-        Self { frames, packet_numbers }
+        Self { frames, packet_numbers, application_frames: None, truncated: None }
+    }
+
+    pub fn add_application_frame(&mut self, frame: serde_json::Value) {
+        match self.application_frames.as_mut() {
+            Some(vec) => vec.push(frame),
+            None => self.application_frames = Some(vec![frame]),
+        }
+    }
+
+    /// Also adds the packet number of the frame, when the frames so far all had one
+    pub fn add_frame(&mut self, frame: QuicFrame, packet_number: Option<u64>) {
+        match (packet_number, self.packet_numbers.as_mut()) {
+            (Some(packet_number), Some(packet_numbers)) if packet_numbers.len() == self.frames.len() => packet_numbers.push(packet_number),
+            (Some(packet_number), None) if self.frames.is_empty() => self.packet_numbers = Some(vec![packet_number]),
+            // The packet numbers have to line up with the frames
+            _ => self.packet_numbers = None
+        }
+
+        self.frames.push(frame);
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Keeps only the first `len` frames (and their packet numbers) and marks the event as truncated
+    pub(crate) fn truncate_frames(&mut self, len: usize) {
+        self.frames.truncate(len);
+
+        if let Some(packet_numbers) = self.packet_numbers.as_mut() {
+            packet_numbers.truncate(len);
+        }
+
+        self.truncated = Some(true);
     }
 }
 
@@ -589,7 +997,8 @@ impl FramesProcessed {
 /// This helps make clear the flow of data, how long data remains in various buffers, and the overheads introduced by individual layers.
 /// This event is only for data in QUIC streams. For data in QUIC Datagram Frames, see the DatagramDataMoved event.
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct StreamDataMoved {
     stream_id: Option<u64>,
     offset: Option<u64>,
@@ -623,7 +1032,8 @@ impl StreamDataMoved {
 /// This helps make clear the flow of data, how long data remains in various buffers, and the overheads introduced by individual layers.
 /// This event is only for data in QUIC Datagram Frames. For data in QUIC streams, see the StreamDataMoved event
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct DatagramDataMoved {
     /// Byte length of the moved data
     length: Option<u64>,
@@ -638,10 +1048,28 @@ impl DatagramDataMoved {
     }
 }
 
+/// Emitted when the data of a QUIC Datagram Frame is dropped above QUIC (e.g., expired in the application's send queue), not sent or delivered.
+/// For dropped UDP datagrams, see the UdpDatagramDropped event
+#[skip_serializing_none]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct DatagramDropped {
+    /// Byte length of the dropped data
+    length: Option<u64>,
+    reason: Option<DatagramDroppedReason>
+}
+
+impl DatagramDropped {
+    pub fn new(length: Option<u64>, reason: Option<DatagramDroppedReason>) -> Self {
+        Self { length, reason }
+    }
+}
+
 /// Provides additional information when attempting (client-side) connection migration.
 /// Generally speaking, connection migration goes through two phases: a probing phase (which is not always needed/present), and a migration phase (which can be abandoned upon error).
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct MigrationStateUpdated {
     old: Option<MigrationState>,
     new: MigrationState,
@@ -652,7 +1080,9 @@ pub struct MigrationStateUpdated {
     path_remote: Option<PathEndpointInfo>,
 
     /// The information for traffic coming in at the local endpoint
-    path_local: Option<PathEndpointInfo>
+    path_local: Option<PathEndpointInfo>,
+
+    trigger: Option<MigrationTrigger>
 }
 
 impl MigrationStateUpdated {
@@ -661,14 +1091,16 @@ impl MigrationStateUpdated {
         new: MigrationState,
         path_id: Option<PathId>,
         path_remote: Option<PathEndpointInfo>,
-        path_local: Option<PathEndpointInfo>
+        path_local: Option<PathEndpointInfo>,
+        trigger: Option<MigrationTrigger>
     ) -> Self {
-        Self { old, new, path_id, path_remote, path_local }
+        Self { old, new, path_id, path_remote, path_local, trigger }
     }
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct KeyUpdated {
     key_type: KeyType,
     old: Option<HexString>,
@@ -686,7 +1118,8 @@ impl KeyUpdated {
 }
 
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct KeyDiscarded {
     key_type: KeyType,
     key: Option<HexString>,
@@ -702,20 +1135,43 @@ impl KeyDiscarded {
     }
 }
 
+/// Emitted when a key is no longer used to protect new packets, but is kept to decrypt packets that are still in flight.
+/// Once the key is removed from memory, a KeyDiscarded event should follow.
+#[skip_serializing_none]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct KeyRetired {
+    key_type: KeyType,
+    key: Option<HexString>,
+
+    // Needed for 1-RTT key updates
+    key_phase: Option<u64>,
+    trigger: Option<KeyRetireTrigger>
+}
+
+impl KeyRetired {
+    pub fn new(key_type: KeyType, key: Option<HexString>, key_phase: Option<u64>, trigger: Option<KeyRetireTrigger>) -> Self {
+        Self { key_type, key, key_phase, trigger }
+    }
+}
+
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct RecoveryParametersSet {
     // Loss detection, see RFC 9002 Appendix A.2
     /// In amount of packets
     reordering_threshold: Option<u16>,
 
     /// As RTT multiplier
+    #[serde(serialize_with = "serialize_finite_f32")]
     time_threshold: Option<f32>,
 
     /// In ms
     timer_granularity: u16,
 
     /// In ms
+    #[serde(serialize_with = "serialize_finite_f32")]
     initial_rtt: Option<f32>,
 
     // Congestion control, see RFC 9002 Appendix B.2
@@ -728,6 +1184,7 @@ pub struct RecoveryParametersSet {
     // Note that this could change when max_datagram_size changes
     /// In bytes
     minimum_congestion_window: Option<u64>,
+    #[serde(serialize_with = "serialize_finite_f32")]
     loss_reduction_factor: Option<f32>,
 
     /// As PTO multiplier
@@ -765,13 +1222,18 @@ impl RecoveryParametersSet {
 /// In order to make logging easier, implementations may log values even if they are the same as previously reported values.
 /// However, applications should try to log only actual updates to values.
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct RecoveryMetricsUpdated {
     // Loss detection, see RFC 9002 Appendix A.3
     // All following RTT fields are expressed in ms
+    #[serde(serialize_with = "serialize_finite_f32")]
     min_rtt: Option<f32>,
+    #[serde(serialize_with = "serialize_finite_f32")]
     smoothed_rtt: Option<f32>,
+    #[serde(serialize_with = "serialize_finite_f32")]
     latest_rtt: Option<f32>,
+    #[serde(serialize_with = "serialize_finite_f32")]
     rtt_variance: Option<f32>,
     pto_count: Option<u16>,
 
@@ -817,12 +1279,54 @@ impl RecoveryMetricsUpdated {
             pacing_rate
         }
     }
+
+    /// Sets the RTT values from durations (converted to ms)
+    pub fn set_rtts_from_durations(&mut self, min_rtt: Option<Duration>, smoothed_rtt: Option<Duration>, latest_rtt: Option<Duration>, rtt_variance: Option<Duration>) {
+        self.min_rtt = min_rtt.map(duration_to_ms);
+        self.smoothed_rtt = smoothed_rtt.map(duration_to_ms);
+        self.latest_rtt = latest_rtt.map(duration_to_ms);
+        self.rtt_variance = rtt_variance.map(duration_to_ms);
+    }
+
+    /// Reports values that usually point to a congestion control bug and returns whether none were found
+    ///
+    /// i.e., bytes_in_flight above the congestion_window, a zero congestion_window, or ssthresh below `minimum_congestion_window`
+    pub fn validate(&self, minimum_congestion_window: Option<u64>) -> bool {
+        let mut valid = true;
+
+        if self.congestion_window == Some(0) {
+            diagnostic::report(format_args!("The congestion_window is 0"));
+            valid = false;
+        }
+
+        if let (Some(bytes_in_flight), Some(congestion_window)) = (self.bytes_in_flight, self.congestion_window) {
+            if bytes_in_flight > congestion_window {
+                diagnostic::report(format_args!("The bytes_in_flight ({bytes_in_flight}) exceed the congestion_window ({congestion_window})"));
+                valid = false;
+            }
+        }
+
+        if let (Some(ssthresh), Some(minimum_congestion_window)) = (self.ssthresh, minimum_congestion_window) {
+            if ssthresh < minimum_congestion_window {
+                diagnostic::report(format_args!("The ssthresh ({ssthresh}) is below the minimum congestion window ({minimum_congestion_window})"));
+                valid = false;
+            }
+        }
+
+        valid
+    }
+}
+
+// Converts in f64 first, so sub-millisecond precision isn't lost for long durations
+fn duration_to_ms(duration: Duration) -> f32 {
+    (duration.as_secs_f64() * 1000.0) as f32
 }
 
 /// Indicates when the congestion controller enters a significant new state and changes its behaviour.
 /// The values of the event's fields are intentionally unspecified here in order to support different Congestion Control algorithms, as these typically have different states and even different implementations of these states across stacks.
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct CongestionStateUpdated {
     old: Option<String>,
     new: String,
@@ -843,7 +1347,8 @@ impl CongestionStateUpdated {
 /// 
 /// In order to indicate an active timer's timeout update, a new set event is used.
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct LossTimerUpdated {
     // Called "mode" in RFC 9002 A.9
     timer_type: Option<TimerType>,
@@ -851,18 +1356,37 @@ pub struct LossTimerUpdated {
     event_type: EventType,
 
     /// If event_type == Set: delta time is in ms from this event's timestamp until when the timer will trigger
+    #[serde(serialize_with = "serialize_finite_f32")]
     delta: Option<f32>
 }
 
 impl LossTimerUpdated {
     pub fn new(timer_type: Option<TimerType>, packet_number_space: Option<PacketNumberSpace>, event_type: EventType, delta: Option<f32>) -> Self {
+        debug_assert!(
+            event_type == EventType::Set || delta.is_none(),
+            "delta should only be set when the event_type is 'set'"
+        );
+
         Self { timer_type, packet_number_space, event_type, delta }
     }
+
+    pub fn set(timer_type: Option<TimerType>, packet_number_space: Option<PacketNumberSpace>, delta: f32) -> Self {
+        Self::new(timer_type, packet_number_space, EventType::Set, Some(delta))
+    }
+
+    pub fn expired(timer_type: Option<TimerType>, packet_number_space: Option<PacketNumberSpace>) -> Self {
+        Self::new(timer_type, packet_number_space, EventType::Expired, None)
+    }
+
+    pub fn cancelled(timer_type: Option<TimerType>, packet_number_space: Option<PacketNumberSpace>) -> Self {
+        Self::new(timer_type, packet_number_space, EventType::Cancelled, None)
+    }
 }
 
 /// Emitted when a packet is deemed lost by loss detection.
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct PacketLost {
     // Should include at least the packet_type and packet_number
     header: Option<PacketHeader>,
@@ -879,10 +1403,35 @@ impl PacketLost {
 
         Self { header, frames, is_mtu_probe_packet, trigger }
     }
+
+    pub fn get_header(&self) -> Option<&PacketHeader> {
+        self.header.as_ref()
+    }
+}
+
+/// Emitted when loss detection declares multiple packets lost at once (with the same trigger).
+#[skip_serializing_none]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct PacketsLost {
+    // Should include at least the packet_type and packet_number
+    headers: Vec<PacketHeader>,
+    trigger: Option<PacketLostTrigger>
+}
+
+impl PacketsLost {
+    pub fn new(headers: Vec<PacketHeader>, trigger: Option<PacketLostTrigger>) -> Self {
+        Self { headers, trigger }
+    }
+
+    pub fn get_headers(&self) -> &Vec<PacketHeader> {
+        &self.headers
+    }
 }
 
 /// Indicates which data was marked for retransmission upon detection of packet loss.
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct MarkedForRetransmit {
     frames: Vec<QuicFrame>
 }
@@ -895,7 +1444,8 @@ impl MarkedForRetransmit {
 
 /// Indicates a progression in the ECN state machine
 #[skip_serializing_none]
-#[derive(Serialize)]
+#[derive(Clone, Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct EcnStateUpdated {
     old: Option<EcnState>,
     new: EcnState
@@ -906,3 +1456,29 @@ impl EcnStateUpdated {
         Self { old, new }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostic;
+
+    use super::ParametersSet;
+
+    fn parameters(resumption_allowed: Option<bool>, early_data_enabled: Option<bool>) -> ParametersSet {
+        ParametersSet::new(None, resumption_allowed, early_data_enabled, None, None, None, None, None, None, None, None, None, None, None, None, None, None, None, None, None, None, None, None, None, None)
+    }
+
+    #[test]
+    fn early_data_without_resumption_is_reported() {
+        let (consistent, reports) = diagnostic::capture(|| parameters(Some(false), Some(true)).check());
+
+        assert!(!consistent);
+        assert_eq!(reports.len(), 1);
+
+        for (resumption_allowed, early_data_enabled) in [(Some(true), Some(true)), (Some(false), Some(false)), (None, Some(true))] {
+            let (consistent, reports) = diagnostic::capture(|| parameters(resumption_allowed, early_data_enabled).check());
+
+            assert!(consistent);
+            assert!(reports.is_empty());
+        }
+    }
+}
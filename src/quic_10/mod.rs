@@ -1,2 +1,4 @@
 pub mod data;
 pub mod events;
+pub mod tracker;
+pub mod tracer;
@@ -0,0 +1,89 @@
+use crate::{events::Event, util::HexString, writer::{PacketNum, PacketNumSpace, QlogWriter}};
+
+use super::{data::{KeyType, KeyUpdateTrigger, Owner, PacketNumberSpace, QuicFrame}, events::{ConnectionClosed, PacketReceived, PacketSent, RecoveryMetricsUpdated}};
+
+/// Logs the events of a single connection through the global `QlogWriter` without passing its connection ID
+#[derive(Clone, Debug)]
+pub struct ConnectionTracer {
+    cid: String
+}
+
+impl ConnectionTracer {
+    pub fn new(cid: String) -> Self {
+        Self { cid }
+    }
+
+    pub fn get_cid(&self) -> &String {
+        &self.cid
+    }
+
+    pub fn cache_packet_sent(&self, packet_num: PacketNum, packet: PacketSent) {
+        QlogWriter::cache_quic_packet_sent(self.cid.clone(), packet_num, packet);
+    }
+
+    pub fn packet_sent_add_frame(&self, packet_num: PacketNum, frame: QuicFrame) {
+        QlogWriter::quic_packet_sent_add_frame(self.cid.clone(), packet_num, frame);
+    }
+
+    pub fn update_packet_length(&self, packet_num: PacketNum, payload_length: u16) {
+        QlogWriter::update_packet_length(self.cid.clone(), packet_num, payload_length);
+    }
+
+    pub fn packets_sent(&self, packet_nums: Vec<PacketNum>) {
+        QlogWriter::log_quic_packets_sent(self.cid.clone(), packet_nums);
+    }
+
+    pub fn cache_packet_received(&self, packet_num: PacketNum, packet: PacketReceived) {
+        QlogWriter::cache_quic_packet_received(self.cid.clone(), packet_num, packet);
+    }
+
+    pub fn packet_received_add_frame(&self, packet_num: PacketNum, frame: QuicFrame) {
+        QlogWriter::quic_packet_received_add_frame(self.cid.clone(), packet_num, frame);
+    }
+
+    /// Logs a packet that was cached with `cache_packet_received()`
+    pub fn cached_packet_received(&self, packet_num: PacketNum) {
+        QlogWriter::log_quic_packets_received(self.cid.clone(), packet_num);
+    }
+
+    pub fn packet_received(&self, packet_num: PacketNum, packet: PacketReceived) {
+        QlogWriter::log_quic_packet_received(self.cid.clone(), packet_num, packet);
+    }
+
+    pub fn cache_frames_processed(&self, packet_num: PacketNum) {
+        QlogWriter::cache_quic_frames_processed(self.cid.clone(), packet_num);
+    }
+
+    pub fn frames_processed_add_frame(&self, packet_num: PacketNum, frame: QuicFrame) {
+        QlogWriter::quic_frames_processed_add_frame(self.cid.clone(), packet_num, frame);
+    }
+
+    /// Logs the frames added since `cache_frames_processed()`
+    pub fn frames_processed(&self, packet_num: PacketNum) {
+        QlogWriter::log_quic_frames_processed(self.cid.clone(), packet_num);
+    }
+
+    pub fn packets_acked(&self, packet_number_space: PacketNumSpace, packet_numbers: Vec<u64>) {
+        QlogWriter::log_quic_packets_acked(self.cid.clone(), packet_number_space, packet_numbers);
+    }
+
+    pub fn packet_number_skipped(&self, packet_number_space: Option<PacketNumberSpace>, packet_number: u64) {
+        QlogWriter::log_event(Event::quic_10_packet_number_skipped(packet_number_space, packet_number, Some(self.cid.clone())));
+    }
+
+    pub fn key_updated(&self, key_type: KeyType, old: Option<HexString>, new: Option<HexString>, trigger: Option<KeyUpdateTrigger>) {
+        QlogWriter::log_quic_key_updated(self.cid.clone(), key_type, old, new, trigger);
+    }
+
+    pub fn connection_closed(&self, connection_closed: ConnectionClosed) {
+        QlogWriter::log_event(Event::quic_10_connection_closed_from(connection_closed, Some(self.cid.clone())));
+    }
+
+    pub fn connection_closed_idle_timeout(&self, owner: Owner) {
+        QlogWriter::log_event(Event::quic_10_connection_closed_idle_timeout(owner, Some(self.cid.clone())));
+    }
+
+    pub fn recovery_metrics_updated(&self, recovery_metrics_updated: RecoveryMetricsUpdated) {
+        QlogWriter::log_event(Event::quic_10_recovery_metrics_updated_from(recovery_metrics_updated, Some(self.cid.clone())));
+    }
+}
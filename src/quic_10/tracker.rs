@@ -0,0 +1,364 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::{diagnostic, events::{Event, RawInfo}, util::HexString};
+
+use super::{data::{PathChallengeFrame, PathResponseFrame, StreamSide, StreamState, StreamType}, events::PacketSent};
+
+/// Remembers the last logged state of every stream side of a single connection and reports illegal transitions
+#[derive(Default)]
+pub struct StreamStateTracker {
+    states: HashMap<(u64, Option<StreamSide>), StreamState>
+}
+
+impl StreamStateTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores the new state of the stream side and returns the previous one, reporting illegal transitions
+    pub fn update(&mut self, stream_id: u64, stream_side: Option<StreamSide>, new: StreamState) -> Option<StreamState> {
+        let old = self.states.insert((stream_id, stream_side), new);
+
+        if let Some(old) = old {
+            if !old.can_transition_to(&new) {
+                diagnostic::report(format_args!("Illegal stream state transition for stream {stream_id} ({stream_side:?}): {old:?} -> {new:?}"));
+            }
+        }
+
+        old
+    }
+
+    pub fn get_state(&self, stream_id: u64, stream_side: Option<StreamSide>) -> Option<&StreamState> {
+        self.states.get(&(stream_id, stream_side))
+    }
+
+    /// Creates a stream_state_updated event with the `old` state of the stream side filled in
+    pub fn stream_state_updated(&mut self, stream_id: u64, stream_type: Option<StreamType>, new: StreamState, stream_side: Option<StreamSide>, cid: Option<String>) -> Event {
+        let old = self.update(stream_id, stream_side, new);
+
+        Event::quic_10_stream_state_updated(stream_id, stream_type, old, new, stream_side, cid)
+    }
+}
+
+/// Reports overlaps and gaps in the stream_data_moved events of a single connection between two layers
+///
+/// Call `update()` for every `Event::quic_10_stream_data_moved()` of the connection
+#[derive(Default)]
+pub struct StreamFlowTracker {
+    end_offsets: HashMap<u64, u64>
+}
+
+impl StreamFlowTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the moved bytes and returns the end offset of the stream's data moved so far, reporting overlaps and gaps
+    pub fn update(&mut self, stream_id: u64, offset: u64, length: u64) -> u64 {
+        let end_offset = self.end_offsets.entry(stream_id).or_default();
+
+        if offset < *end_offset {
+            diagnostic::report(format_args!("Overlapping data moved for stream {stream_id}: offset {offset}, but the data up to offset {end_offset} was already moved"));
+        }
+        else if offset > *end_offset {
+            diagnostic::report(format_args!("Gap in the data moved for stream {stream_id}: offset {offset}, but the data was only moved up to offset {end_offset}"));
+        }
+
+        *end_offset = (*end_offset).max(offset.saturating_add(length));
+        *end_offset
+    }
+
+    pub fn get_end_offset(&self, stream_id: u64) -> Option<u64> {
+        self.end_offsets.get(&stream_id).copied()
+    }
+}
+
+/// Collects the packets that are coalesced into the same UDP datagram (sharing a `datagram_id`)
+#[derive(Default)]
+pub struct DatagramAssembler {
+    datagrams: HashMap<u32, CoalescedPackets>
+}
+
+#[derive(Default)]
+struct CoalescedPackets {
+    packet_count: usize,
+    length: u64
+}
+
+impl DatagramAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the packet to its datagram, packets without a datagram_id or raw.length are reported and skipped
+    pub fn add_packet(&mut self, packet: &PacketSent) {
+        let Some(datagram_id) = packet.get_datagram_id() else {
+            diagnostic::report(format_args!("Can't add a packet without a datagram_id to a datagram, skipping it"));
+            return;
+        };
+
+        let Some(length) = packet.get_raw().and_then(RawInfo::get_length) else {
+            diagnostic::report(format_args!("Can't add a packet without a raw.length to datagram {datagram_id}, skipping it"));
+            return;
+        };
+
+        let datagram = self.datagrams.entry(datagram_id).or_default();
+
+        datagram.packet_count += 1;
+        datagram.length += length;
+    }
+
+    /// The number of packets added to the datagram so far
+    pub fn packet_count(&self, datagram_id: u32) -> usize {
+        self.datagrams.get(&datagram_id).map_or(0, |datagram| datagram.packet_count)
+    }
+
+    /// Creates the udp_datagrams_sent event of the datagram and stops tracking it
+    pub fn udp_datagrams_sent(&mut self, datagram_id: u32, cid: Option<String>) -> Option<Event> {
+        let datagram = self.datagrams.remove(&datagram_id)?;
+
+        Some(Event::quic_10_udp_datagrams_sent(
+            Some(1),
+            Some(vec![RawInfo::new(Some(datagram.length), None)]),
+            None,
+            Some(vec![datagram_id]),
+            cid
+        ))
+    }
+}
+
+/// Reassembles the CRYPTO frame payloads of one encryption level (e.g., to log the full ClientHello)
+pub struct CryptoReassembler {
+    chunks: BTreeMap<u64, Vec<u8>>,
+    max_data_len: usize
+}
+
+impl CryptoReassembler {
+    /// At most `max_data_len` bytes of the reassembled data get logged
+    pub fn new(max_data_len: usize) -> Self {
+        Self { chunks: BTreeMap::new(), max_data_len }
+    }
+
+    /// Adds the payload of a CRYPTO frame, a longer payload at the same offset replaces a shorter one
+    pub fn add_frame(&mut self, offset: u64, data: &[u8]) {
+        let chunk = self.chunks.entry(offset).or_default();
+
+        if data.len() > chunk.len() {
+            *chunk = data.to_vec();
+        }
+    }
+
+    /// The data that has been received without gaps, starting at offset 0
+    pub fn contiguous_data(&self) -> Vec<u8> {
+        let mut data: Vec<u8> = Vec::new();
+
+        for (offset, chunk) in &self.chunks {
+            let end = data.len() as u64;
+
+            if *offset > end {
+                break;
+            }
+
+            // Only the part of the chunk after the data that's already there
+            let skip = (end - offset) as usize;
+
+            if skip < chunk.len() {
+                data.extend_from_slice(&chunk[skip..]);
+            }
+        }
+
+        data
+    }
+
+    /// The reassembled data, `length` is the contiguous length even when the data gets truncated
+    pub fn raw_info(&self) -> RawInfo {
+        let data = self.contiguous_data();
+
+        RawInfo::new_with_max_data_len(Some(data.len() as u64), Some(&data), self.max_data_len)
+    }
+}
+
+/// Matches the PATH_RESPONSE frames of a single connection to the PATH_CHALLENGE frames they answer
+#[derive(Default)]
+pub struct PathValidationTracker {
+    outstanding_challenges: HashSet<HexString>
+}
+
+impl PathValidationTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_challenge(&mut self, challenge: &PathChallengeFrame) {
+        if let Some(data) = challenge.get_data() {
+            self.outstanding_challenges.insert(data.clone());
+        }
+    }
+
+    /// Returns whether the response answers an outstanding challenge, reporting it otherwise
+    pub fn add_response(&mut self, response: &PathResponseFrame) -> bool {
+        let Some(data) = response.get_data() else {
+            return false;
+        };
+
+        if self.outstanding_challenges.remove(data) {
+            return true;
+        }
+
+        if self.outstanding_challenges.is_empty() {
+            diagnostic::report(format_args!("Unsolicited PATH_RESPONSE with data {data}, there are no outstanding PATH_CHALLENGEs"));
+        }
+        else {
+            diagnostic::report(format_args!("PATH_RESPONSE with data {data} doesn't match any of the outstanding PATH_CHALLENGEs"));
+        }
+
+        false
+    }
+
+    pub fn outstanding_challenge_count(&self) -> usize {
+        self.outstanding_challenges.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostic;
+    use crate::events::RawInfo;
+    use crate::quic_10::data::{GranularStreamState, PacketHeader, PacketType, PathChallengeFrame, PathResponseFrame, StreamSide, StreamState};
+    use crate::quic_10::events::PacketSent;
+
+    use super::{CryptoReassembler, DatagramAssembler, PathValidationTracker, StreamFlowTracker, StreamStateTracker};
+
+    fn header(packet_number: u64) -> PacketHeader {
+        PacketHeader::new(None, PacketType::OneRtt, None, Some(packet_number), None, None, None, None, None, None, None, None)
+    }
+
+    #[test]
+    fn stream_sides_are_tracked_separately() {
+        let mut tracker = StreamStateTracker::new();
+
+        let send = StreamState::GranularStreamState(GranularStreamState::Send);
+        let receive = StreamState::GranularStreamState(GranularStreamState::Receive);
+        let data_sent = StreamState::GranularStreamState(GranularStreamState::DataSent);
+        let size_known = StreamState::GranularStreamState(GranularStreamState::SizeKnown);
+
+        let ((), reports) = diagnostic::capture(|| {
+            assert_eq!(tracker.update(0, Some(StreamSide::Sending), send), None);
+            assert_eq!(tracker.update(0, Some(StreamSide::Receiving), receive), None);
+            assert_eq!(tracker.update(0, Some(StreamSide::Sending), data_sent), Some(send));
+            assert_eq!(tracker.update(0, Some(StreamSide::Receiving), size_known), Some(receive));
+        });
+
+        assert!(reports.is_empty());
+        assert_eq!(tracker.get_state(0, Some(StreamSide::Sending)), Some(&data_sent));
+        assert_eq!(tracker.get_state(0, Some(StreamSide::Receiving)), Some(&size_known));
+    }
+
+    #[test]
+    fn illegal_transitions_are_reported() {
+        let mut tracker = StreamStateTracker::new();
+
+        let data_read = StreamState::GranularStreamState(GranularStreamState::DataRead);
+        let receive = StreamState::GranularStreamState(GranularStreamState::Receive);
+
+        let (old, reports) = diagnostic::capture(|| {
+            tracker.update(4, Some(StreamSide::Receiving), data_read);
+            tracker.update(4, Some(StreamSide::Receiving), receive)
+        });
+
+        // The new state is stored regardless
+        assert_eq!(old, Some(data_read));
+        assert_eq!(tracker.get_state(4, Some(StreamSide::Receiving)), Some(&receive));
+        assert_eq!(reports.len(), 1);
+    }
+
+    #[test]
+    fn datagram_assembler_skips_incomplete_packets() {
+        let mut assembler = DatagramAssembler::new();
+
+        let ((), reports) = diagnostic::capture(|| {
+            assembler.add_packet(&PacketSent::builder(header(0)).build());
+            assembler.add_packet(&PacketSent::builder(header(1)).datagram_id(7).build());
+        });
+
+        assert_eq!(assembler.packet_count(7), 0);
+        assert_eq!(reports.len(), 2);
+
+        assembler.add_packet(&PacketSent::builder(header(2)).datagram_id(7).raw(RawInfo::new(Some(100), None)).build());
+        assembler.add_packet(&PacketSent::builder(header(3)).datagram_id(7).raw(RawInfo::new(Some(50), None)).build());
+        assert_eq!(assembler.packet_count(7), 2);
+
+        let event = assembler.udp_datagrams_sent(7, None).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&event.to_json().unwrap()).unwrap();
+
+        assert_eq!(json["data"]["count"], 1);
+        assert_eq!(json["data"]["raw"][0]["length"], 150);
+        assert!(assembler.udp_datagrams_sent(7, None).is_none());
+    }
+
+    #[test]
+    fn stream_flow_overlaps_and_gaps_are_reported() {
+        let mut tracker = StreamFlowTracker::new();
+
+        let ((), reports) = diagnostic::capture(|| {
+            assert_eq!(tracker.update(0, 0, 100), 100);
+            assert_eq!(tracker.update(0, 100, 50), 150);
+            assert_eq!(tracker.update(4, 0, 10), 10);
+        });
+
+        assert!(reports.is_empty());
+
+        let ((), reports) = diagnostic::capture(|| {
+            assert_eq!(tracker.update(0, 120, 10), 150);
+            assert_eq!(tracker.update(0, 200, 10), 210);
+        });
+
+        assert_eq!(reports.len(), 2);
+        assert!(reports[0].starts_with("Overlapping data moved for stream 0"));
+        assert!(reports[1].starts_with("Gap in the data moved for stream 0"));
+        assert_eq!(tracker.get_end_offset(4), Some(10));
+        assert_eq!(tracker.get_end_offset(8), None);
+    }
+
+    #[test]
+    fn crypto_reassembler_logs_the_contiguous_data() {
+        let mut reassembler = CryptoReassembler::new(4);
+
+        reassembler.add_frame(4, &[4, 5, 6]);
+        reassembler.add_frame(10, &[10]);
+        assert!(reassembler.contiguous_data().is_empty());
+
+        reassembler.add_frame(0, &[0, 1]);
+        reassembler.add_frame(0, &[0, 1, 2, 3]);
+        reassembler.add_frame(2, &[2, 3, 4]);
+        reassembler.add_frame(0, &[0]);
+        assert_eq!(reassembler.contiguous_data(), [0, 1, 2, 3, 4, 5, 6]);
+
+        let raw = serde_json::to_value(reassembler.raw_info()).unwrap();
+
+        assert_eq!(raw["length"], 7);
+        assert_eq!(raw["data"], "00010203");
+    }
+
+    #[test]
+    fn path_responses_are_matched_to_challenges() {
+        let mut tracker = PathValidationTracker::new();
+
+        tracker.add_challenge(&PathChallengeFrame::from_bytes(&[1; 8], None));
+        tracker.add_challenge(&PathChallengeFrame::from_bytes(&[2; 8], None));
+        assert_eq!(tracker.outstanding_challenge_count(), 2);
+
+        let (matched, reports) = diagnostic::capture(|| [
+            tracker.add_response(&PathResponseFrame::from_bytes(&[2; 8], None)),
+            tracker.add_response(&PathResponseFrame::from_bytes(&[3; 8], None)),
+            tracker.add_response(&PathResponseFrame::from_bytes(&[1; 8], None)),
+            tracker.add_response(&PathResponseFrame::from_bytes(&[1; 8], None))
+        ]);
+
+        assert_eq!(matched, [true, false, true, false]);
+        assert_eq!(tracker.outstanding_challenge_count(), 0);
+        assert_eq!(reports.len(), 2);
+        assert!(reports[0].contains("doesn't match any of the outstanding"));
+        assert!(reports[1].starts_with("Unsolicited PATH_RESPONSE"));
+    }
+}
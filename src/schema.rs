@@ -0,0 +1,14 @@
+use schemars::schema_for;
+use serde_json::Value;
+
+use crate::{events::Event, logfile::QlogFileSeq};
+
+/// JSON Schema of the events that get logged (for the enabled protocol features)
+pub fn dump() -> Value {
+    serde_json::to_value(schema_for!(Event)).unwrap()
+}
+
+/// JSON Schema of the first record of a qlog file (the file and trace details)
+pub fn dump_file_details() -> Value {
+    serde_json::to_value(schema_for!(QlogFileSeq)).unwrap()
+}
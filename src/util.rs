@@ -1,9 +1,14 @@
 use std::fmt::Write;
 
+use serde::{ser::{SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple, SerializeTupleStruct, SerializeTupleVariant}, Serialize, Serializer};
+
+use crate::events::PathId;
+
+#[cfg(feature = "quic-10")]
+use crate::diagnostic;
+
 pub const MAX_LOG_DATA_LEN: usize = 64;
 
-pub type PathId = String;
-pub type GroupId = String;
 pub type HexString = String;
 
 pub fn bytes_to_hexstring(bytes: &[u8]) -> HexString {
@@ -13,9 +18,235 @@ pub fn bytes_to_hexstring(bytes: &[u8]) -> HexString {
     })
 }
 
+/// Only hex-encodes the first MAX_LOG_DATA_LEN bytes
+pub fn bytes_to_truncated_hexstring(bytes: &[u8]) -> HexString {
+    bytes_to_hexstring(&bytes[..bytes.len().min(MAX_LOG_DATA_LEN)])
+}
+
 pub fn is_empty_or_none(path: &Option<PathId>) -> bool {
     match path {
         Some(p) => p.is_empty(),
         None => true,
     }
 }
+
+/// Logs NaN and infinity (which JSON can't represent) as null
+#[cfg(feature = "quic-10")]
+pub fn serialize_finite_f32<S: Serializer>(value: &Option<f32>, serializer: S) -> Result<S::Ok, S::Error> {
+    match value {
+        Some(v) if !v.is_finite() => {
+            diagnostic::report(format_args!("Non-finite value {v} can't be represented in JSON, logging null instead"));
+            serializer.serialize_none()
+        },
+        _ => value.serialize(serializer)
+    }
+}
+
+/// Current and new name of renamed struct fields
+pub(crate) type FieldRenames = &'static [(&'static str, &'static str)];
+
+/// Serializes the value with the fields of its (nested) structs renamed, but not the keys of maps
+pub(crate) struct RenameFields<'a, T: ?Sized> {
+    value: &'a T,
+    renames: FieldRenames
+}
+
+impl<'a, T: ?Sized> RenameFields<'a, T> {
+    pub(crate) fn new(value: &'a T, renames: FieldRenames) -> Self {
+        Self { value, renames }
+    }
+}
+
+impl<T: ?Sized + Serialize> Serialize for RenameFields<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.value.serialize(FieldRenamer { inner: serializer, renames: self.renames })
+    }
+}
+
+/// Serializer (or compound serializer) that renames the fields of every nested struct
+struct FieldRenamer<S> {
+    inner: S,
+    renames: FieldRenames
+}
+
+impl<S> FieldRenamer<S> {
+    fn rename(&self, name: &'static str) -> &'static str {
+        self.renames.iter().find(|(current, _)| *current == name).map_or(name, |(_, new)| new)
+    }
+}
+
+macro_rules! forward_serialize {
+    ($($method:ident($type:ty)),*) => {
+        $(
+            fn $method(self, value: $type) -> Result<S::Ok, S::Error> {
+                self.inner.$method(value)
+            }
+        )*
+    };
+}
+
+impl<S: Serializer> Serializer for FieldRenamer<S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = FieldRenamer<S::SerializeSeq>;
+    type SerializeTuple = FieldRenamer<S::SerializeTuple>;
+    type SerializeTupleStruct = FieldRenamer<S::SerializeTupleStruct>;
+    type SerializeTupleVariant = FieldRenamer<S::SerializeTupleVariant>;
+    type SerializeMap = FieldRenamer<S::SerializeMap>;
+    type SerializeStruct = FieldRenamer<S::SerializeStruct>;
+    type SerializeStructVariant = FieldRenamer<S::SerializeStructVariant>;
+
+    forward_serialize!(
+        serialize_bool(bool),
+        serialize_i8(i8), serialize_i16(i16), serialize_i32(i32), serialize_i64(i64), serialize_i128(i128),
+        serialize_u8(u8), serialize_u16(u16), serialize_u32(u32), serialize_u64(u64), serialize_u128(u128),
+        serialize_f32(f32), serialize_f64(f64),
+        serialize_char(char), serialize_str(&str), serialize_bytes(&[u8])
+    );
+
+    fn serialize_none(self) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_none()
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_some(&RenameFields::new(value, self.renames))
+    }
+
+    fn serialize_unit(self) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_unit()
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_unit_struct(name)
+    }
+
+    fn serialize_unit_variant(self, name: &'static str, variant_index: u32, variant: &'static str) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_unit_variant(name, variant_index, variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, name: &'static str, value: &T) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_newtype_struct(name, &RenameFields::new(value, self.renames))
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, name: &'static str, variant_index: u32, variant: &'static str, value: &T) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_newtype_variant(name, variant_index, variant, &RenameFields::new(value, self.renames))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, S::Error> {
+        Ok(FieldRenamer { inner: self.inner.serialize_seq(len)?, renames: self.renames })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, S::Error> {
+        Ok(FieldRenamer { inner: self.inner.serialize_tuple(len)?, renames: self.renames })
+    }
+
+    fn serialize_tuple_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeTupleStruct, S::Error> {
+        Ok(FieldRenamer { inner: self.inner.serialize_tuple_struct(name, len)?, renames: self.renames })
+    }
+
+    fn serialize_tuple_variant(self, name: &'static str, variant_index: u32, variant: &'static str, len: usize) -> Result<Self::SerializeTupleVariant, S::Error> {
+        Ok(FieldRenamer { inner: self.inner.serialize_tuple_variant(name, variant_index, variant, len)?, renames: self.renames })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, S::Error> {
+        Ok(FieldRenamer { inner: self.inner.serialize_map(len)?, renames: self.renames })
+    }
+
+    fn serialize_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeStruct, S::Error> {
+        Ok(FieldRenamer { inner: self.inner.serialize_struct(name, len)?, renames: self.renames })
+    }
+
+    fn serialize_struct_variant(self, name: &'static str, variant_index: u32, variant: &'static str, len: usize) -> Result<Self::SerializeStructVariant, S::Error> {
+        Ok(FieldRenamer { inner: self.inner.serialize_struct_variant(name, variant_index, variant, len)?, renames: self.renames })
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+// The compound serializers that take values without a name
+macro_rules! impl_rename_compound {
+    ($($trait:ident::$method:ident),*) => {
+        $(
+            impl<C: $trait> $trait for FieldRenamer<C> {
+                type Ok = C::Ok;
+                type Error = C::Error;
+
+                fn $method<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), C::Error> {
+                    self.inner.$method(&RenameFields::new(value, self.renames))
+                }
+
+                fn end(self) -> Result<C::Ok, C::Error> {
+                    self.inner.end()
+                }
+            }
+        )*
+    };
+}
+
+impl_rename_compound!(
+    SerializeSeq::serialize_element,
+    SerializeTuple::serialize_element,
+    SerializeTupleStruct::serialize_field,
+    SerializeTupleVariant::serialize_field
+);
+
+impl<C: SerializeMap> SerializeMap for FieldRenamer<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), C::Error> {
+        self.inner.serialize_key(key)
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), C::Error> {
+        self.inner.serialize_value(&RenameFields::new(value, self.renames))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeStruct> SerializeStruct for FieldRenamer<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<(), C::Error> {
+        let key = self.rename(key);
+
+        self.inner.serialize_field(key, &RenameFields::new(value, self.renames))
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), C::Error> {
+        let key = self.rename(key);
+
+        self.inner.skip_field(key)
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeStructVariant> SerializeStructVariant for FieldRenamer<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<(), C::Error> {
+        let key = self.rename(key);
+
+        self.inner.serialize_field(key, &RenameFields::new(value, self.renames))
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), C::Error> {
+        let key = self.rename(key);
+
+        self.inner.skip_field(key)
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
@@ -1,96 +1,275 @@
-use std::{collections::VecDeque, env, fs::File, io::{BufWriter, Write}, sync::{mpsc::{self, Sender}, LazyLock, Mutex}, thread};
+use std::{collections::VecDeque, env, fs::File, io::{BufWriter, Write}, path::Path, sync::{atomic::{AtomicBool, Ordering}, mpsc::{self, Sender}, Arc, LazyLock, Mutex}, thread};
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+use serde_json::ser::PrettyFormatter;
 
 #[cfg(feature = "quic-10")]
-use chrono::Utc;
+use crate::util::HexString;
 
-use serde::Serialize;
+use crate::util::RenameFields;
+use crate::{clock::{self, Clock, TimePrecision}, diagnostic::{self, DiagnosticHandler}, events::{Event, GroupId, PathId}, logfile::{CommonFields, Configuration, LogFile, QlogFileSeq, ReferenceTime, TimeFormat, TraceSeq, VantagePoint}};
 
-use crate::{events::Event, logfile::{CommonFields, LogFile, QlogFileSeq, ReferenceTime, TimeFormat, TraceSeq, VantagePoint}};
+#[cfg(feature = "quic-10")]
+use crate::events::EventTime;
 
 #[cfg(feature = "quic-10")]
-use crate::quic_10::data::Quic10EventData;
+use crate::quic_10::data::{PacketHeader, PacketNumberSpace, PacketType, Quic10EventData};
 
 #[cfg(feature = "quic-10")]
-use crate::quic_10::{data::QuicFrame, events::{PacketReceived, PacketSent}};
+use crate::quic_10::{data::{KeyType, KeyUpdateTrigger, QuicFrame}, events::{FramesProcessed, PacketReceived, PacketSent, PacketsAcked, RecoveryMetricsUpdated}};
 
 #[cfg(feature = "moq-transfork")]
 use crate::moq_transfork::data::StreamType;
 
+#[cfg(feature = "moq-transfork")]
+use crate::events::Protocol;
+
 // Static variable so that a logger variable doesn't need to be passed to every function wherein logging occurs
 static QLOG_WRITER: LazyLock<Mutex<QlogWriter>> = LazyLock::new(|| Mutex::new(QlogWriter::init()));
 
+// Set once when the writer is initialized, read without taking the writer lock
+static QLOG_ENABLED: AtomicBool = AtomicBool::new(false);
+
 pub struct QlogWriter {
-	sender: Option<Sender<String>>,
+	output: Option<WriterOutput>,
 	file_details_written: bool,
+	// Kept to start rotated files with the same file details
+	file_details: Option<QlogFileSeq>,
+    auto_file_details: bool,
+    legacy_file_fields: bool,
+    // The sequence number of the next event, None when sequence numbers are disabled
+    next_seq: Option<u64>,
+    record_format: RecordFormat,
+    field_names: FieldNames,
+    max_record_size: Option<usize>,
+    max_events_per_group: Option<usize>,
+    // (last use, number of events logged or dropped) per group, only counted when there's a maximum
+    group_event_counts: HashMap<GroupId, (u64, usize)>,
+    group_event_uses: u64,
+    reorder_window: Option<usize>,
+    // Events held back to be written in order of time, only used when there's a reorder window
+    reorder_buffer: Vec<Event>,
     #[allow(dead_code)]
 	cached_events: VecDeque<Event>,
     #[cfg(feature = "quic-10")]
-    cached_sent_quic_packets: HashMap<String, PacketSent>,
+    cached_sent_quic_packets: HashMap<String, (PacketSent, EventTime)>,
+    // Send times of the sent packets that haven't been acknowledged yet
+    #[cfg(feature = "quic-10")]
+    unacked_sent_quic_packets: HashMap<String, EventTime>,
     #[cfg(feature = "quic-10")]
-    cached_received_quic_packets: HashMap<String, (PacketReceived, i64)>
+    cached_received_quic_packets: HashMap<String, (PacketReceived, EventTime)>,
+    #[cfg(feature = "quic-10")]
+    cached_quic_frames_processed: HashMap<String, (FramesProcessed, EventTime)>,
+    #[cfg(feature = "quic-10")]
+    max_cached_connections: Option<usize>,
+    // (last use, number of cached and unacknowledged packets) per connection, only tracked when there's a limit
+    #[cfg(feature = "quic-10")]
+    cached_connection_uses: HashMap<String, (u64, usize)>,
+    #[cfg(feature = "quic-10")]
+    cache_uses: u64,
+    #[cfg(feature = "quic-10")]
+    quic_key_phases: HashMap<(String, KeyType), u64>,
+    // Last logged recovery metrics per group, only kept when the deduplication is enabled
+    #[cfg(feature = "quic-10")]
+    last_recovery_metrics: Option<HashMap<Option<GroupId>, RecoveryMetricsUpdated>>
 }
 
 impl QlogWriter {
 	const RECORD_SEPARATOR: &[u8] = &[0x1E];
 	const LINE_FEED: &[u8] = &[0x0A];
+	// The events of the least recently logged group stop being counted when more groups are counted
+	const MAX_COUNTED_GROUPS: usize = 10_000;
 
 	fn init() -> Self {
 		match env::var("QLOGFILE") {
 			Ok(qlog_file_path) => {
 				match File::create(qlog_file_path) {
 					Ok(file) => {
-                        let writer = BufWriter::new(file);
-                        let (sender, receiver) = mpsc::channel::<String>();
-
-                        // TODO: Maybe add more error handling
-	                    // Flushes write buffer after every log, otherwise won't write to file when exiting the program using ^C
-                        thread::spawn(move || {
-                            let mut writer = writer;
-                            while let Ok(message) = receiver.recv() {
-                                if writer.write_all(Self::RECORD_SEPARATOR).is_err() { break; }
-                                if writer.write_all(message.as_bytes()).is_err() { break; }
-                                if writer.write_all(Self::LINE_FEED).is_err() { break; }
-                                if writer.flush().is_err() { break; }
-                            }
-                        });
-
-                        Self {
-                            sender: Some(sender),
-                            file_details_written: false,
-                            cached_events: VecDeque::default(),
-                            #[cfg(feature = "quic-10")]
-                            cached_sent_quic_packets: HashMap::default(),
-                            #[cfg(feature = "quic-10")]
-                            cached_received_quic_packets: HashMap::default()
-                        }
+                        let sender = Self::spawn_writer_thread(BufWriter::new(file));
+
+                        QLOG_ENABLED.store(true, Ordering::Release);
+
+                        Self::new(Some(WriterOutput::Thread(sender)))
                     },
 					Err(e) => panic!("Error creating qlog file: {e}")
 				}
 			},
-			Err(_) => Self {
-                sender: None,
-                file_details_written: true,
-                cached_events: VecDeque::default(),
-                #[cfg(feature = "quic-10")]
-                cached_sent_quic_packets: HashMap::default(),
-                #[cfg(feature = "quic-10")]
-                cached_received_quic_packets: HashMap::default()
+			Err(_) => Self::new(None)
+		}
+	}
+
+    fn new(output: Option<WriterOutput>) -> Self {
+        Self {
+            // Nothing gets written without an output, so there are no file details to wait for
+            file_details_written: output.is_none(),
+            output,
+            file_details: None,
+            auto_file_details: false,
+            legacy_file_fields: false,
+            next_seq: None,
+            record_format: RecordFormat::default(),
+            field_names: FieldNames::default(),
+            max_record_size: None,
+            max_events_per_group: None,
+            group_event_counts: HashMap::default(),
+            group_event_uses: 0,
+            reorder_window: None,
+            reorder_buffer: Vec::new(),
+            cached_events: VecDeque::default(),
+            #[cfg(feature = "quic-10")]
+            cached_sent_quic_packets: HashMap::default(),
+            #[cfg(feature = "quic-10")]
+            unacked_sent_quic_packets: HashMap::default(),
+            #[cfg(feature = "quic-10")]
+            cached_received_quic_packets: HashMap::default(),
+            #[cfg(feature = "quic-10")]
+            cached_quic_frames_processed: HashMap::default(),
+            #[cfg(feature = "quic-10")]
+            max_cached_connections: None,
+            #[cfg(feature = "quic-10")]
+            cached_connection_uses: HashMap::default(),
+            #[cfg(feature = "quic-10")]
+            cache_uses: 0,
+            #[cfg(feature = "quic-10")]
+            quic_key_phases: HashMap::default(),
+            #[cfg(feature = "quic-10")]
+            last_recovery_metrics: None
+        }
+    }
+
+    fn spawn_writer_thread(writer: impl Write + Send + 'static) -> Sender<WriterMessage> {
+        let (sender, receiver) = mpsc::channel::<WriterMessage>();
+
+        // TODO: Maybe add more error handling
+        // Flushes write buffer after every log, otherwise won't write to file when exiting the program using ^C
+        thread::spawn(move || {
+            let mut writer = writer;
+            while let Ok(message) = receiver.recv() {
+                match message {
+                    WriterMessage::Record(record) => {
+                        if Self::write_record(&mut writer, &record).is_err() { break; }
+                    },
+                    WriterMessage::Flush(ack) => {
+                        if writer.flush().is_err() { break; }
+                        let _ = ack.send(());
+                    }
+                }
+            }
+        });
+
+        sender
+    }
+
+    /// Blocks until every record logged so far has been written
+    pub fn flush() {
+        // Need to introduce this extra scope so the lock gets dropped before waiting
+        let ack_receiver = {
+            let mut qlog_writer = QLOG_WRITER.lock().unwrap();
+
+            qlog_writer.drain_reorder_buffer(0);
+
+            match qlog_writer.output {
+                Some(WriterOutput::Thread(ref sender)) => {
+                    let (ack_sender, ack_receiver) = mpsc::channel::<()>();
+
+                    if sender.send(WriterMessage::Flush(ack_sender)).is_err() {
+                        return;
+                    }
+
+                    ack_receiver
+                },
+                Some(WriterOutput::Inline(ref mut writer)) => {
+                    if let Err(e) = writer.flush() {
+                        diagnostic::report(format_args!("Error flushing qlog writer: {e}"));
+                    }
+
+                    return;
+                },
+                None => return
             }
+        };
+
+        let _ = ack_receiver.recv();
+    }
+
+    /// Returns a guard that flushes the writer when dropped (e.g., at the end of `main()`)
+    pub fn flush_guard() -> FlushGuard {
+        FlushGuard { _private: () }
+    }
+
+    /// Redirects all logging to an in-memory buffer, resetting the writer state
+    #[cfg(any(test, feature = "test-util"))]
+    pub fn init_in_memory() -> InMemoryHandle {
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let sender = Self::spawn_writer_thread(SharedBuffer(Arc::clone(&buffer)));
+
+        *QLOG_WRITER.lock().unwrap() = Self::new(Some(WriterOutput::Thread(sender)));
+
+        QLOG_ENABLED.store(true, Ordering::Release);
+
+        InMemoryHandle { buffer }
+    }
+
+    /// Redirects all logging to `writer` on the calling thread, resetting the writer state
+    pub fn init_sync(writer: impl Write + Send + 'static) {
+        *QLOG_WRITER.lock().unwrap() = Self::new(Some(WriterOutput::Inline(Box::new(writer))));
+
+        QLOG_ENABLED.store(true, Ordering::Release);
+    }
+
+	/// Finishes the current log file and continues logging to a new file with the same file details
+	pub fn rotate(path: impl AsRef<Path>) -> std::io::Result<()> {
+		let file = File::create(path)?;
+
+		// Everything logged to the current file gets written before it's closed
+		Self::flush();
+
+		let mut qlog_writer = QLOG_WRITER.lock().unwrap();
+
+		// Dropping the old sender stops the old writer thread once it wrote the remaining records
+		qlog_writer.output = Some(WriterOutput::Thread(Self::spawn_writer_thread(BufWriter::new(file))));
+
+		match qlog_writer.file_details.take() {
+			Some(file_details) => qlog_writer.write_file_details(file_details),
+			None => qlog_writer.file_details_written = false
 		}
+
+		QLOG_ENABLED.store(true, Ordering::Release);
+
+		Ok(())
+	}
+
+	/// Returns whether events will actually be written (i.e., the `QLOGFILE` environment variable is set)
+	///
+	/// Use this to avoid building expensive events when logging is disabled:
+	///
+	/// ```ignore
+	/// if QlogWriter::is_enabled() {
+	///     QlogWriter::log_event(build_expensive_event());
+	/// }
+	/// ```
+	pub fn is_enabled() -> bool {
+		LazyLock::force(&QLOG_WRITER);
+
+		QLOG_ENABLED.load(Ordering::Acquire)
 	}
 
 	/// Logs the needed details so qlog file readers can interpret the logs correctly
-	pub fn log_file_details(file_title: Option<String>, file_description: Option<String>, trace_title: Option<String>, trace_description: Option<String>, vantage_point: Option<VantagePoint>, custom_fields: Option<HashMap<String, String>>) {
+	pub fn log_file_details(file_title: Option<String>, file_description: Option<String>, trace_title: Option<String>, trace_description: Option<String>, configuration: Option<Configuration>, vantage_point: Option<VantagePoint>, custom_fields: Option<BTreeMap<String, String>>) {
 		let mut qlog_writer = QLOG_WRITER.lock().unwrap();
 
-		if let Some(ref sender) = qlog_writer.sender {
-			let log_file_details = LogFile::new(file_title, file_description);
+		if qlog_writer.output.is_some() {
+			let mut log_file_details = LogFile::new(file_title, file_description);
+
+			if qlog_writer.legacy_file_fields {
+				log_file_details.set_legacy_fields(None);
+			}
 
             let common_fields = match custom_fields {
                 Some(fields) => CommonFields::new(
-                    Some("".to_string()),
+                    Some(PathId::from("")),
                     Some(TimeFormat::default()),
 			        Some(ReferenceTime::default()),
                     None,
@@ -99,49 +278,451 @@ impl QlogWriter {
                 None => CommonFields::default(),
             };
 
-			let trace = TraceSeq::new(trace_title, trace_description, Some(common_fields), vantage_point);
+			let trace = TraceSeq::new(trace_title, trace_description, configuration, Some(common_fields), vantage_point);
+
+			qlog_writer.write_file_details(QlogFileSeq::new(log_file_details, trace));
+		}
+	}
+
+	/// Same as `log_file_details()`, but with file details built by the caller
+	pub fn log_custom_file_details(mut qlog_file_seq: QlogFileSeq) {
+		let mut qlog_writer = QLOG_WRITER.lock().unwrap();
+
+		if qlog_writer.output.is_some() {
+			if qlog_writer.legacy_file_fields {
+				qlog_file_seq.ensure_legacy_fields();
+			}
+
+			qlog_writer.write_file_details(qlog_file_seq);
+		}
+	}
+
+	fn write_file_details(&mut self, qlog_file_seq: QlogFileSeq) {
+		match self.field_names {
+			FieldNames::Spec => self.log(&qlog_file_seq),
+			FieldNames::Legacy => self.log(&qlog_file_seq.with_legacy_field_names())
+		}
+
+		self.file_details = Some(qlog_file_seq);
+		self.file_details_written = true;
+	}
+
+	/// Panics if the file details haven't been logged yet and automatic file details are disabled
+	fn ensure_file_details(&mut self) {
+		if self.file_details_written {
+			return;
+		}
+
+		if !self.auto_file_details {
+			panic!("Log the qlog file details before logging events, call 'QlogWriter::log_file_details()' somewhere in the beginning of the program");
+		}
 
-			let qlog_file_seq = QlogFileSeq::new(log_file_details, trace);
+		diagnostic::report(format_args!("Event logged before the qlog file details, logging default file details, call 'QlogWriter::log_file_details()' somewhere in the beginning of the program"));
 
-			Self::log(sender, &qlog_file_seq);
+		let mut log_file_details = LogFile::new(None, None);
 
-			qlog_writer.file_details_written = true;
+		if self.legacy_file_fields {
+			log_file_details.set_legacy_fields(None);
 		}
+
+		let trace = TraceSeq::new(None, None, None, Some(CommonFields::default()), None);
+
+		self.write_file_details(QlogFileSeq::new(log_file_details, trace));
 	}
 
-    #[cfg_attr(feature = "moq-transfork", allow(unreachable_code))]
 	pub fn log_event(event: Event) {
+        // Only MoQ events need the session stream caching, other protocols can be logged in the same process
         #[cfg(feature = "moq-transfork")]
-        return Self::log_moq_event(event);
+        if event.protocol() == Protocol::MoqTransfork {
+            return Self::log_moq_event(event);
+        }
+
+		let mut qlog_writer = QLOG_WRITER.lock().unwrap();
 
-		let qlog_writer = QLOG_WRITER.lock().unwrap();
+		qlog_writer.ensure_file_details();
 
-		if !qlog_writer.file_details_written {
-			panic!("Log the qlog file details before logging events, call 'QlogWriter::log_file_details()' somewhere in the beginning of the program");
+		#[cfg(feature = "quic-10")]
+		if qlog_writer.is_duplicate_recovery_metrics(&event) {
+			return;
 		}
 
-		if let Some(ref sender) = qlog_writer.sender {
-			Self::log(sender, &event);
+		#[cfg(feature = "quic-10")]
+		qlog_writer.forget_quic_state(&event);
+
+		qlog_writer.write_event(event);
+	}
+
+	/// Holds the event back if there's a reorder window, otherwise writes it right away
+	fn write_event(&mut self, event: Event) {
+		match self.reorder_window {
+			Some(window) => {
+				self.reorder_buffer.push(event);
+				self.drain_reorder_buffer(window);
+			},
+			None => self.log_event_record(event)
 		}
 	}
 
-	fn log(sender: &Sender<String>, data: &impl Serialize) {
-		let json = serde_json::to_string_pretty(data).unwrap();
+	/// Holds back up to `window` events and writes them in order of time (only sorted within the window)
+	///
+	/// Held back events are only written by `flush()`, so hold a `flush_guard()` to keep them at exit
+	pub fn set_reorder_window(window: Option<usize>) {
+		let mut qlog_writer = QLOG_WRITER.lock().unwrap();
 
-		if let Err(e) = sender.send(json) {
-            eprintln!("Error sending log message: {e}");
-        }
+		qlog_writer.reorder_window = window;
+		qlog_writer.drain_reorder_buffer(window.unwrap_or(0));
+	}
+
+	/// Writes the earliest held back events until at most `remaining` are left
+	fn drain_reorder_buffer(&mut self, remaining: usize) {
+		while self.reorder_buffer.len() > remaining {
+			let earliest = self.reorder_buffer.iter()
+				.enumerate()
+				.min_by_key(|(_, event)| event.get_time().as_micros())
+				.map(|(i, _)| i)
+				.unwrap();
+
+			let event = self.reorder_buffer.remove(earliest);
+
+			self.log_event_record(event);
+		}
+	}
+
+	/// Logs default file details instead of panicking when an event comes before `log_file_details()`
+	pub fn set_auto_file_details(enabled: bool) {
+		let mut qlog_writer = QLOG_WRITER.lock().unwrap();
+
+		qlog_writer.auto_file_details = enabled;
+	}
+
+	/// Sets how each record gets serialized
+	pub fn set_record_format(record_format: RecordFormat) {
+		let mut qlog_writer = QLOG_WRITER.lock().unwrap();
+
+		qlog_writer.record_format = record_format;
+	}
+
+	/// Renames a known set of the crate's own fields for tools that expect older qlog drafts
+	pub fn set_field_names(field_names: FieldNames) {
+		let mut qlog_writer = QLOG_WRITER.lock().unwrap();
+
+		qlog_writer.field_names = field_names;
+	}
+
+	/// Also emits the legacy `qlog_version` and `qlog_format` fields in the file details
+	pub fn set_legacy_file_fields(enabled: bool) {
+		let mut qlog_writer = QLOG_WRITER.lock().unwrap();
+
+		qlog_writer.legacy_file_fields = enabled;
+	}
+
+	/// Gives every event logged afterwards an increasing `seq` field, starting at 0
+	pub fn set_sequence_numbers(enabled: bool) {
+		let mut qlog_writer = QLOG_WRITER.lock().unwrap();
+
+		// Keeps counting when sequence numbers were already enabled
+		qlog_writer.next_seq = match enabled {
+			true => Some(qlog_writer.next_seq.unwrap_or(0)),
+			false => None
+		};
+	}
+
+	/// Limits the number of events logged per group (e.g., per connection) and drops the rest
+	///
+	/// Only the 10 000 most recently logged groups are counted
+	pub fn set_max_events_per_group(max_events: Option<usize>) {
+		let mut qlog_writer = QLOG_WRITER.lock().unwrap();
+
+		qlog_writer.max_events_per_group = max_events;
+
+		if max_events.is_none() {
+			qlog_writer.group_event_counts.clear();
+		}
+	}
+
+	/// Sets the precision of the timestamps of all events created afterwards (milliseconds by default)
+	pub fn set_time_precision(time_precision: TimePrecision) {
+		clock::set_time_precision(time_precision);
+	}
+
+	/// Stamps all events created afterwards with the time of the given clock instead of the system time
+	pub fn set_clock(clock: Arc<dyn Clock>) {
+		clock::set_clock(clock);
+	}
+
+	/// Passes the crate's diagnostics to `handler` instead of printing them to stderr
+	///
+	/// The handler must not log events because it can be called while the writer is locked
+	pub fn set_diagnostic_handler(handler: Option<DiagnosticHandler>) {
+		diagnostic::set_handler(handler);
+	}
+
+	/// Limits the size (in bytes) of every event record by dropping frames from QUIC packet events
+	pub fn set_max_record_size(max_record_size: Option<usize>) {
+		let mut qlog_writer = QLOG_WRITER.lock().unwrap();
+
+		qlog_writer.max_record_size = max_record_size;
+	}
+
+	fn to_string_indented(data: &(impl Serialize + ?Sized), indent_width: usize) -> serde_json::Result<String> {
+		let indent = vec![b' '; indent_width];
+		let mut json = Vec::new();
+		let mut serializer = serde_json::Serializer::with_formatter(&mut json, PrettyFormatter::with_indent(&indent));
+
+		data.serialize(&mut serializer)?;
+
+		// serde_json only produces valid UTF-8
+		Ok(String::from_utf8(json).unwrap())
+	}
+
+	/// None if the data couldn't be serialized
+	fn to_record(&self, data: &impl Serialize) -> Option<String> {
+		let json = match self.field_names {
+			FieldNames::Spec => self.serialize_record(data),
+			FieldNames::Legacy => self.serialize_record(&RenameFields::new(data, FieldNames::LEGACY_RENAMES))
+		};
+
+		match json {
+			Ok(json) => Some(json),
+			Err(e) => {
+				diagnostic::report(format_args!("Error serializing log message, skipping it: {e}"));
+				None
+			}
+		}
+	}
+
+	fn serialize_record(&self, data: &(impl Serialize + ?Sized)) -> serde_json::Result<String> {
+		// serde_json escapes all control characters inside strings, so a record never contains a raw record separator
+		match self.record_format {
+			RecordFormat::Pretty => serde_json::to_string_pretty(data),
+			RecordFormat::PrettyIndent(indent_width) => Self::to_string_indented(data, indent_width),
+			RecordFormat::Compact => serde_json::to_string(data)
+		}
+	}
+
+	fn log(&mut self, data: &impl Serialize) {
+		if self.output.is_some() {
+			if let Some(json) = self.to_record(data) {
+				self.send_record(json);
+			}
+		}
+	}
+
+	/// Same as `log()`, but keeps the record under the maximum record size where possible
+	fn log_event_record(&mut self, mut event: Event) {
+		if self.output.is_none() {
+			return;
+		}
+
+		if self.is_over_group_limit(&event) {
+			return;
+		}
+
+		// Events inherit the group_id of the common fields, so it's left out when it's the same
+		if let Some(trace_group_id) = self.file_details.as_ref().and_then(QlogFileSeq::get_group_id) {
+			if event.get_group_id() == Some(trace_group_id) {
+				event.set_group_id(None);
+			}
+		}
+
+		if let Some(seq) = self.next_seq {
+			event.set_seq(seq);
+			self.next_seq = Some(seq + 1);
+		}
+
+		let Some(json) = self.to_record(&event) else {
+			return;
+		};
+
+		let Some(max_record_size) = self.max_record_size else {
+			return self.send_record(json);
+		};
+
+		#[cfg(feature = "quic-10")]
+		let json = self.truncate_quic_frames(event, json, max_record_size);
+
+		if json.len() > max_record_size {
+			diagnostic::report(format_args!("Record of {} bytes exceeds the maximum record size of {max_record_size} bytes", json.len()));
+		}
+
+		self.send_record(json);
+	}
+
+	/// Counts the event for its group and returns whether the group is over the limit
+	fn is_over_group_limit(&mut self, event: &Event) -> bool {
+		let (Some(max_events), Some(group_id)) = (self.max_events_per_group, event.get_group_id()) else {
+			return false;
+		};
+
+		self.group_event_uses += 1;
+
+		if !self.group_event_counts.contains_key(group_id) && self.group_event_counts.len() >= Self::MAX_COUNTED_GROUPS {
+			if let Some(least_recent) = self.group_event_counts.iter().min_by_key(|(_, (last_use, _))| *last_use).map(|(group_id, _)| group_id.clone()) {
+				self.group_event_counts.remove(&least_recent);
+			}
+		}
+
+		let (last_use, count) = self.group_event_counts.entry(group_id.clone()).or_default();
+
+		*last_use = self.group_event_uses;
+		*count += 1;
+
+		// Only report the first dropped event of the group
+		if *count == max_events + 1 {
+			diagnostic::report(format_args!("Event limit of {max_events} reached for group {group_id:?}, dropping its next events"));
+		}
+
+		let is_over_limit = *count > max_events;
+
+		// The connection_closed event is the last event of a QUIC connection
+		#[cfg(feature = "quic-10")]
+		if event.quic_10_is_connection_closed() {
+			self.group_event_counts.remove(group_id);
+		}
+
+		is_over_limit
+	}
+
+	/// Halves the number of frames until the record fits
+	#[cfg(feature = "quic-10")]
+	fn truncate_quic_frames(&self, mut event: Event, mut json: String, max_record_size: usize) -> String {
+		let mut frame_count = event.quic_10_frame_count().unwrap_or(0);
+
+		while json.len() > max_record_size && frame_count > 0 {
+			frame_count /= 2;
+
+			event.quic_10_truncate_frames(frame_count);
+
+			match self.to_record(&event) {
+				Some(truncated) => json = truncated,
+				None => break
+			}
+		}
+
+		json
+	}
+
+	fn send_record(&mut self, json: String) {
+		let Some(ref mut output) = self.output else {
+			return;
+		};
+
+		match output {
+			WriterOutput::Thread(sender) => {
+				if let Err(e) = sender.send(WriterMessage::Record(json)) {
+					diagnostic::report(format_args!("Error sending log message: {e}"));
+				}
+			},
+			WriterOutput::Inline(writer) => {
+				if let Err(e) = Self::write_record(writer, &json) {
+					diagnostic::report(format_args!("Error writing log message: {e}"));
+				}
+			}
+		}
+	}
+
+	fn write_record(writer: &mut impl Write, record: &str) -> std::io::Result<()> {
+		writer.write_all(Self::RECORD_SEPARATOR)?;
+		writer.write_all(record.as_bytes())?;
+		writer.write_all(Self::LINE_FEED)?;
+		writer.flush()
+	}
+}
+
+/// Flushes the writer when dropped, see `QlogWriter::flush_guard()`
+#[must_use = "the writer is flushed when the guard is dropped"]
+pub struct FlushGuard {
+	_private: ()
+}
+
+impl Drop for FlushGuard {
+	fn drop(&mut self) {
+		QlogWriter::flush();
+	}
+}
+
+/// Where serialized records go
+enum WriterOutput {
+	/// Handed to the background writer thread
+	Thread(Sender<WriterMessage>),
+	/// Written on the calling thread, see `QlogWriter::init_sync()`
+	Inline(Box<dyn Write + Send>)
+}
+
+enum WriterMessage {
+	Record(String),
+	/// Acknowledged once everything before it has been written
+	Flush(Sender<()>)
+}
+
+/// Gives access to the output of a writer initialized with `QlogWriter::init_in_memory()`
+#[cfg(any(test, feature = "test-util"))]
+pub struct InMemoryHandle {
+	buffer: Arc<Mutex<Vec<u8>>>
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl InMemoryHandle {
+	/// Waits until everything logged so far has been written to the buffer
+	pub fn flush(&self) {
+		QlogWriter::flush();
+	}
+
+	/// Everything written so far (call `flush()` first)
+	pub fn contents(&self) -> Vec<u8> {
+		self.buffer.lock().unwrap().clone()
+	}
+}
+
+#[cfg(any(test, feature = "test-util"))]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+#[cfg(any(test, feature = "test-util"))]
+impl Write for SharedBuffer {
+	fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+		self.0.lock().unwrap().write(buf)
+	}
+
+	fn flush(&mut self) -> std::io::Result<()> {
+		Ok(())
 	}
 }
 
+/// Serialization of a single JSON text sequence record
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RecordFormat {
+	/// Human readable, records span multiple lines
+	#[default]
+	Pretty,
+	/// Same as Pretty, but indented with the given number of spaces instead of 2
+	PrettyIndent(usize),
+	/// Every record is a single line without interior newlines (strict RFC 7464 parsers)
+	Compact
+}
+
+/// Field names used in the records
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FieldNames {
+	/// The names of the current qlog drafts
+	#[default]
+	Spec,
+	/// The names of older qlog drafts (e.g., `protocol_type` instead of `protocol_types`)
+	Legacy
+}
+
+impl FieldNames {
+	/// Current and legacy name of the fields renamed in events
+	pub const LEGACY_RENAMES: &[(&str, &str)] = &[
+		("error_code_bytes", "error_code_value")
+	];
+}
+
 #[cfg(feature = "moq-transfork")]
 impl QlogWriter {
     fn log_moq_event(event: Event) {
         let mut qlog_writer = QLOG_WRITER.lock().unwrap();
 
-		if !qlog_writer.file_details_written {
-			panic!("Log the qlog file details before logging events, call 'QlogWriter::log_file_details()' somewhere in the beginning of the program");
-		}
+		qlog_writer.ensure_file_details();
 
 		let is_session_started_event = event.moq_is_session_started_client();
 		let mut session_stream_event_option: Option<Event> = None;
@@ -150,7 +731,7 @@ impl QlogWriter {
 			session_stream_event_option = qlog_writer.cached_events.pop_front();
 		}
 
-		if let Some(ref sender) = qlog_writer.sender {
+		if qlog_writer.output.is_some() {
 			if Self::is_session_stream_without_id(&event) {
 				qlog_writer.cached_events.push_back(event);
 			}
@@ -158,22 +739,36 @@ impl QlogWriter {
 				if let Some(mut session_stream_event) = session_stream_event_option {
 					session_stream_event.set_group_id(event.get_group_id());
 
-					Self::log(sender, &session_stream_event);
-					Self::log(sender, &event);
+					qlog_writer.write_event(session_stream_event);
+					qlog_writer.write_event(event);
 				}
 			}
 			else {
-				Self::log(sender, &event);
+				qlog_writer.write_event(event);
 			}
 		}
     }
 
+	/// Only builds the frame_created event when logging is enabled
+	pub fn log_moq_frame_created(payload_length: Option<u64>, payload: Option<&[u8]>, tracing_id: u64) {
+		if Self::is_enabled() {
+			Self::log_event(Event::moq_frame_created(payload_length, payload, tracing_id));
+		}
+	}
+
+	/// Only builds the frame_parsed event when logging is enabled
+	pub fn log_moq_frame_parsed(payload_length: Option<u64>, payload: Option<&[u8]>, tracing_id: u64) {
+		if Self::is_enabled() {
+			Self::log_event(Event::moq_frame_parsed(payload_length, payload, tracing_id));
+		}
+	}
+
 	fn is_session_stream_without_id(event: &Event) -> bool {
 		if event.get_name() != "moq-transfork-03:stream_created" && event.get_name() != "moq-transfork-03:stream_parsed" {
 			return false;
 		}
 
-		if !event.get_group_id().is_some_and(|group_id| group_id == "0") {
+		if event.moq_tracing_id() != Some(0) {
 			return false;
 		}
 
@@ -189,10 +784,16 @@ impl QlogWriter {
         let key = format!("{}:{}", cid, packet_num);
         let log_key = format!("{}...:{}", cid.get(0..5).unwrap(), packet_num);
 
-        let existing_value = qlog_writer.cached_sent_quic_packets.insert(key, packet);
+        let time = clock::now();
+
+        let existing_unacked = qlog_writer.unacked_sent_quic_packets.insert(key.clone(), time);
+
+        let existing_value = qlog_writer.cached_sent_quic_packets.insert(key, (packet, time));
+
+        qlog_writer.use_cached_connection(&cid, usize::from(existing_value.is_none()) + usize::from(existing_unacked.is_none()));
 
         if existing_value.is_some() {
-            println!("KEY {} ALREADY EXISTS, OVERWROTE QUIC SENT PACKET", log_key);
+            diagnostic::report(format_args!("KEY {} ALREADY EXISTS, OVERWROTE QUIC SENT PACKET", log_key));
         }
     }
 
@@ -202,9 +803,10 @@ impl QlogWriter {
         let key = format!("{}:{}", cid, packet_num);
         let log_key = format!("{}...:{}", cid.get(0..5).unwrap(), packet_num);
 
+        // The packet might have been evicted, see `set_max_cached_connections()`
         match qlog_writer.cached_sent_quic_packets.get_mut(&key) {
-            Some(packet) => packet.add_frame(frame),
-            None => panic!("Tried to add a frame to a non-existing sent packet (key = {})", log_key)
+            Some((packet, _)) => packet.add_frame(frame),
+            None => diagnostic::report(format_args!("Tried to add a frame to a non-existing sent packet (key = {})", log_key))
         }
     }
 
@@ -218,12 +820,13 @@ impl QlogWriter {
                 let log_key = format!("{}...:{}", cid.get(0..5).unwrap(), packet_num);
 
                 match qlog_writer.cached_sent_quic_packets.remove(&key) {
-                    Some(packet) => {
+                    Some((packet, time)) => {
+                        qlog_writer.release_cached_connection(&cid, 1);
                         // println!("QUIC packets still cached: {:?}", qlog_writer.cached_sent_quic_packets.keys());
-                        Some(Event::new_quic_10("packet_sent", Quic10EventData::PacketSent(packet), Some(cid.clone())))
+                        Some(Event::new_quic_10_with_time("packet_sent", Quic10EventData::PacketSent(packet), Some(cid.clone()), time))
                     },
                     None => {
-                        println!("Tried to log a non-existing sent packet with key {}", log_key);
+                        diagnostic::report(format_args!("Tried to log a non-existing sent packet with key {}", log_key));
                         None
                     }
                 }
@@ -243,15 +846,15 @@ impl QlogWriter {
         let packet = qlog_writer.cached_sent_quic_packets.get_mut(&key);
 
         match packet {
-            Some(packet_sent) => packet_sent.update_packet_length(payload_length),
-            None => println!("Can't update packet length: no such packet exists"),
+            Some((packet_sent, _)) => packet_sent.update_packet_length(payload_length),
+            None => diagnostic::report(format_args!("Can't update packet length: no such packet exists")),
         }
     }
 
     pub fn cache_quic_packet_received(cid: String, packet_num: PacketNum, packet: PacketReceived) {
         let mut qlog_writer = QLOG_WRITER.lock().unwrap();
 
-        let time = Utc::now().timestamp_millis();
+        let time = clock::now();
 
         let key = format!("{}:{}", cid, packet_num);
         let log_key = format!("{}...:{}", cid.get(0..5).unwrap(), packet_num);
@@ -260,8 +863,10 @@ impl QlogWriter {
 
         let existing_value = qlog_writer.cached_received_quic_packets.insert(key, (packet, time));
 
+        qlog_writer.use_cached_connection(&cid, usize::from(existing_value.is_none()));
+
         if existing_value.is_some() {
-            println!("KEY {} ALREADY EXISTS, OVERWROTE QUIC RECEIVED PACKET", log_key);
+            diagnostic::report(format_args!("KEY {} ALREADY EXISTS, OVERWROTE QUIC RECEIVED PACKET", log_key));
         }
     }
 
@@ -276,7 +881,7 @@ impl QlogWriter {
                 // println!("Added {:?} to packet {}", frame, log_key);
                 packet.add_frame(frame)
             },
-            None => panic!("Tried to add a frame to a non-existing received packet ({})", log_key)
+            None => diagnostic::report(format_args!("Tried to add a frame to a non-existing received packet ({})", log_key))
         }
     }
 
@@ -290,11 +895,12 @@ impl QlogWriter {
 
             match qlog_writer.cached_received_quic_packets.remove(&key) {
                 Some((packet, time)) => {
+                    qlog_writer.release_cached_connection(&cid, 1);
                     // println!("QUIC packets still cached: {:?}", qlog_writer.cached_received_quic_packets.keys());
                     Some(Event::new_quic_10_with_time("packet_received", Quic10EventData::PacketReceived(packet), Some(cid.clone()), time))
                 },
                 None => {
-                    println!("Tried to log a non-existing received packet with key {}", log_key);
+                    diagnostic::report(format_args!("Tried to log a non-existing received packet with key {}", log_key));
                     None
                 }
             }
@@ -304,22 +910,322 @@ impl QlogWriter {
             QlogWriter::log_event(e);
         }
     }
-}
 
-#[cfg(feature = "quic-10")]
-#[derive(Clone, Copy, Debug)]
-pub enum PacketNum {
-    Number(PacketNumSpace, u64),
-    Retry,
-    StatelessReset,
-    VersionNegotiation,
-    Unknown
-}
+    /// Starts collecting the frames of a packet for a single frames_processed event
+    pub fn cache_quic_frames_processed(cid: String, packet_num: PacketNum) {
+        let mut qlog_writer = QLOG_WRITER.lock().unwrap();
 
-#[cfg(feature = "quic-10")]
-impl std::fmt::Display for PacketNum {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
+        let time = clock::now();
+
+        let key = format!("{}:{}", cid, packet_num);
+        let log_key = Self::quic_log_key(&cid, packet_num);
+
+        let existing_value = qlog_writer.cached_quic_frames_processed.insert(key, (FramesProcessed::new(Vec::new(), None), time));
+
+        qlog_writer.use_cached_connection(&cid, usize::from(existing_value.is_none()));
+
+        if existing_value.is_some() {
+            diagnostic::report(format_args!("Frames of packet {} were already being processed, the previous frames are dropped", log_key));
+        }
+    }
+
+    pub fn quic_frames_processed_add_frame(cid: String, packet_num: PacketNum, frame: QuicFrame) {
+        let mut qlog_writer = QLOG_WRITER.lock().unwrap();
+
+        let key = format!("{}:{}", cid, packet_num);
+        let log_key = Self::quic_log_key(&cid, packet_num);
+
+        let packet_number = match packet_num {
+            PacketNum::Number(_, packet_number) => Some(packet_number),
+            _ => None
+        };
+
+        match qlog_writer.cached_quic_frames_processed.get_mut(&key) {
+            Some((frames_processed, _)) => frames_processed.add_frame(frame, packet_number),
+            None => diagnostic::report(format_args!("Tried to add a processed frame to a non-existing packet (key = {})", log_key))
+        }
+    }
+
+    /// Adds an application layer frame (e.g., an HTTP/3 frame) to the processed frames of the packet
+    pub fn quic_frames_processed_add_application_frame(cid: String, packet_num: PacketNum, frame: serde_json::Value) {
+        let mut qlog_writer = QLOG_WRITER.lock().unwrap();
+
+        let key = format!("{}:{}", cid, packet_num);
+        let log_key = Self::quic_log_key(&cid, packet_num);
+
+        match qlog_writer.cached_quic_frames_processed.get_mut(&key) {
+            Some((frames_processed, _)) => frames_processed.add_application_frame(frame),
+            None => diagnostic::report(format_args!("Tried to add a processed application frame to a non-existing packet (key = {})", log_key))
+        }
+    }
+
+    /// Logs the frames_processed event of the frames added since `cache_quic_frames_processed()`
+    pub fn log_quic_frames_processed(cid: String, packet_num: PacketNum) {
+        // Need to introduce this extra scope so the lock gets dropped before logging
+        let event = {
+            let mut qlog_writer = QLOG_WRITER.lock().unwrap();
+
+            let key = format!("{}:{}", cid, packet_num);
+            let log_key = Self::quic_log_key(&cid, packet_num);
+
+            match qlog_writer.cached_quic_frames_processed.remove(&key) {
+                Some((frames_processed, time)) => {
+                    qlog_writer.release_cached_connection(&cid, 1);
+                    Some(Event::new_quic_10_with_time("frames_processed", Quic10EventData::FramesProcessed(frames_processed), Some(cid.clone()), time))
+                },
+                None => {
+                    diagnostic::report(format_args!("Tried to log the processed frames of a non-existing packet with key {}", log_key));
+                    None
+                }
+            }
+        };
+
+        if let Some(e) = event {
+            QlogWriter::log_event(e);
+        }
+    }
+
+    /// Caches and logs a fully parsed received packet in one step
+    pub fn log_quic_packet_received(cid: String, packet_num: PacketNum, packet: PacketReceived) {
+        QlogWriter::cache_quic_packet_received(cid.clone(), packet_num, packet);
+        QlogWriter::log_quic_packets_received(cid, packet_num);
+    }
+
+    /// Logs a packets_acked event with the send time of the largest acknowledged packet that was cached
+    pub fn log_quic_packets_acked(cid: String, packet_number_space: PacketNumSpace, packet_numbers: Vec<u64>) {
+        // Need to introduce this extra scope so the lock gets dropped before logging
+        let largest_acked_sent_time = {
+            let mut qlog_writer = QLOG_WRITER.lock().unwrap();
+
+            // (packet number, send time)
+            let mut largest_acked: Option<(u64, EventTime)> = None;
+
+            for packet_number in &packet_numbers {
+                let packet_num = PacketNum::Number(packet_number_space, *packet_number);
+                let key = format!("{}:{}", cid, packet_num);
+                let log_key = Self::quic_log_key(&cid, packet_num);
+
+                match qlog_writer.unacked_sent_quic_packets.remove(&key) {
+                    Some(time) => {
+                        qlog_writer.release_cached_connection(&cid, 1);
+
+                        if largest_acked.is_none_or(|(largest, _)| *packet_number > largest) {
+                            largest_acked = Some((*packet_number, time));
+                        }
+                    },
+                    None => diagnostic::report(format_args!("Acknowledged packet {} was never sent or was already acknowledged", log_key))
+                }
+            }
+
+            largest_acked.map(|(_, time)| time)
+        };
+
+        let mut packets_acked = PacketsAcked::new(Some(packet_number_space.into()), Some(packet_numbers));
+
+        if let Some(time) = largest_acked_sent_time {
+            packets_acked.set_largest_acked_sent_time(time);
+        }
+
+        QlogWriter::log_event(Event::new_quic_10("packets_acked", Quic10EventData::PacketsAcked(packets_acked), Some(cid)));
+    }
+
+    /// Logs a key_updated event with the key_phase of 1-RTT key updates filled in
+    pub fn log_quic_key_updated(cid: String, key_type: KeyType, old: Option<HexString>, new: Option<HexString>, trigger: Option<KeyUpdateTrigger>) {
+        // Need to introduce this extra scope so the lock gets dropped before logging
+        let key_phase = {
+            let mut qlog_writer = QLOG_WRITER.lock().unwrap();
+
+            let key_phase = qlog_writer.quic_key_phases.entry((cid.clone(), key_type)).or_default();
+
+            if matches!(trigger, Some(KeyUpdateTrigger::LocalUpdate | KeyUpdateTrigger::RemoteUpdate)) {
+                *key_phase += 1;
+                Some(*key_phase)
+            }
+            else {
+                None
+            }
+        };
+
+        QlogWriter::log_event(Event::quic_10_key_updated(key_type, old, new, key_phase, trigger, Some(cid)));
+    }
+
+    /// The number of (sent, received) packets that are cached but haven't been logged yet
+    pub fn cached_packet_counts() -> (usize, usize) {
+        let qlog_writer = QLOG_WRITER.lock().unwrap();
+
+        (qlog_writer.cached_sent_quic_packets.len(), qlog_writer.cached_received_quic_packets.len())
+    }
+
+    /// Drops all cached and unacknowledged packets without logging them
+    pub fn clear_caches() {
+        let mut qlog_writer = QLOG_WRITER.lock().unwrap();
+
+        qlog_writer.cached_sent_quic_packets.clear();
+        qlog_writer.cached_received_quic_packets.clear();
+        qlog_writer.cached_quic_frames_processed.clear();
+        qlog_writer.unacked_sent_quic_packets.clear();
+        qlog_writer.cached_connection_uses.clear();
+    }
+
+    /// Forgets the unacknowledged packets that were lost and the state of closed connections
+    fn forget_quic_state(&mut self, event: &Event) {
+        let Some(GroupId::Text(cid)) = event.get_group_id() else {
+            return;
+        };
+
+        if event.quic_10_is_connection_closed() {
+            let prefix = format!("{}:", cid);
+            let unacked_packets = self.unacked_sent_quic_packets.len();
+
+            self.unacked_sent_quic_packets.retain(|key, _| !key.starts_with(&prefix));
+            self.release_cached_connection(cid, unacked_packets - self.unacked_sent_quic_packets.len());
+            self.quic_key_phases.retain(|(key_cid, _), _| key_cid != cid);
+
+            if let Some(last_recovery_metrics) = self.last_recovery_metrics.as_mut() {
+                last_recovery_metrics.remove(&Some(GroupId::Text(cid.clone())));
+            }
+
+            return;
+        }
+
+        for header in event.quic_10_lost_packet_headers() {
+            if let Some(packet_num) = PacketNum::from_header(header) {
+                if self.unacked_sent_quic_packets.remove(&format!("{}:{}", cid, packet_num)).is_some() {
+                    self.release_cached_connection(cid, 1);
+                }
+            }
+        }
+    }
+
+    /// Skips recovery_metrics_updated events that are the same as the last one of their connection
+    pub fn set_recovery_metrics_dedup(enabled: bool) {
+        let mut qlog_writer = QLOG_WRITER.lock().unwrap();
+
+        qlog_writer.last_recovery_metrics = match enabled {
+            true => Some(qlog_writer.last_recovery_metrics.take().unwrap_or_default()),
+            false => None
+        };
+    }
+
+    /// Remembers the metrics of the event and returns whether they're unchanged for its group
+    fn is_duplicate_recovery_metrics(&mut self, event: &Event) -> bool {
+        let (Some(last_recovery_metrics), Some(metrics)) = (self.last_recovery_metrics.as_mut(), event.quic_10_recovery_metrics()) else {
+            return false;
+        };
+
+        let group_id = event.get_group_id().cloned();
+
+        if last_recovery_metrics.get(&group_id) == Some(metrics) {
+            return true;
+        }
+
+        last_recovery_metrics.insert(group_id, metrics.clone());
+
+        false
+    }
+
+    /// Shortened key for diagnostics (connection IDs can be shorter than the prefix)
+    fn quic_log_key(cid: &str, packet_num: PacketNum) -> String {
+        format!("{}...:{}", cid.get(0..5).unwrap_or(cid), packet_num)
+    }
+
+    /// Limits the number of connections with cached packets by evicting the least recently used one
+    pub fn set_max_cached_connections(max_connections: Option<usize>) {
+        let mut qlog_writer = QLOG_WRITER.lock().unwrap();
+
+        qlog_writer.max_cached_connections = max_connections;
+
+        if max_connections.is_none() {
+            qlog_writer.cached_connection_uses.clear();
+        }
+    }
+
+    /// Called after caching packets of the connection, `added` excludes replaced packets
+    fn use_cached_connection(&mut self, cid: &str, added: usize) {
+        let Some(max_connections) = self.max_cached_connections else {
+            return;
+        };
+
+        self.cache_uses += 1;
+
+        let (last_use, cached_packets) = self.cached_connection_uses.entry(cid.to_string()).or_default();
+
+        *last_use = self.cache_uses;
+
+        *cached_packets += added;
+
+        while self.cached_connection_uses.len() > max_connections {
+            let Some(evicted_cid) = self.cached_connection_uses.iter().min_by_key(|(_, (last_use, _))| *last_use).map(|(cid, _)| cid.clone()) else {
+                return;
+            };
+
+            self.evict_cached_connection(&evicted_cid);
+        }
+    }
+
+    /// Stops tracking the connection once it has no cached or unacknowledged packets left
+    fn release_cached_connection(&mut self, cid: &str, released: usize) {
+        let Some((_, cached_packets)) = self.cached_connection_uses.get_mut(cid) else {
+            return;
+        };
+
+        *cached_packets = cached_packets.saturating_sub(released);
+
+        if *cached_packets == 0 {
+            self.cached_connection_uses.remove(cid);
+        }
+    }
+
+    /// Drops the cached packets of the connection without logging them
+    fn evict_cached_connection(&mut self, cid: &str) {
+        let prefix = format!("{}:", cid);
+
+        let before = self.cached_sent_quic_packets.len() + self.cached_received_quic_packets.len() + self.cached_quic_frames_processed.len();
+
+        self.cached_sent_quic_packets.retain(|key, _| !key.starts_with(&prefix));
+        self.cached_received_quic_packets.retain(|key, _| !key.starts_with(&prefix));
+        self.cached_quic_frames_processed.retain(|key, _| !key.starts_with(&prefix));
+        self.unacked_sent_quic_packets.retain(|key, _| !key.starts_with(&prefix));
+        self.cached_connection_uses.remove(cid);
+
+        let after = self.cached_sent_quic_packets.len() + self.cached_received_quic_packets.len() + self.cached_quic_frames_processed.len();
+
+        diagnostic::report(format_args!("Cached connection limit reached, dropped {} cached packets of the least recently used connection {}", before - after, cid));
+    }
+}
+
+#[cfg(feature = "quic-10")]
+#[derive(Clone, Copy, Debug)]
+pub enum PacketNum {
+    Number(PacketNumSpace, u64),
+    Retry,
+    StatelessReset,
+    VersionNegotiation,
+    Unknown
+}
+
+#[cfg(feature = "quic-10")]
+impl PacketNum {
+    /// None if the header doesn't have a packet number
+    fn from_header(header: &PacketHeader) -> Option<Self> {
+        let packet_number = header.get_packet_number();
+
+        match header.get_packet_type() {
+            PacketType::Initial => packet_number.map(|n| Self::Number(PacketNumSpace::Initial, n)),
+            PacketType::Handshake => packet_number.map(|n| Self::Number(PacketNumSpace::Handshake, n)),
+            PacketType::ZeroRtt | PacketType::OneRtt => packet_number.map(|n| Self::Number(PacketNumSpace::Data, n)),
+            PacketType::Retry => Some(Self::Retry),
+            PacketType::StatelessReset => Some(Self::StatelessReset),
+            PacketType::VersionNegotiation => Some(Self::VersionNegotiation),
+            PacketType::Unknown => None
+        }
+    }
+}
+
+#[cfg(feature = "quic-10")]
+impl std::fmt::Display for PacketNum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
             PacketNum::Number(s, n) => write!(f, "{}:{}", s, n),
             PacketNum::Retry => write!(f, "Retry"),
             PacketNum::StatelessReset => write!(f, "StatelessReset"),
@@ -347,3 +1253,613 @@ impl std::fmt::Display for PacketNumSpace {
         }
     }
 }
+
+#[cfg(feature = "quic-10")]
+impl From<PacketNumSpace> for PacketNumberSpace {
+    fn from(value: PacketNumSpace) -> Self {
+        match value {
+            PacketNumSpace::Initial => PacketNumberSpace::Initial,
+            PacketNumSpace::Handshake => PacketNumberSpace::Handshake,
+            PacketNumSpace::Data => PacketNumberSpace::ApplicationData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::{Arc, Mutex, MutexGuard};
+
+	use serde_json::Value;
+
+	use crate::clock::{ManualClock, SystemClock};
+	use crate::diagnostic;
+	use crate::events::Event;
+
+	use crate::logfile::{CommonFields, LogFile, QlogFileSeq, TraceSeq};
+
+	use super::{FieldNames, InMemoryHandle, QlogWriter, RecordFormat, QLOG_WRITER};
+
+	// The writer is global, so the tests that log can't run in parallel
+	static WRITER_LOCK: Mutex<()> = Mutex::new(());
+
+	fn lock_writer() -> MutexGuard<'static, ()> {
+		WRITER_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+	}
+
+	/// Writer with compact records that already logged its file details
+	fn init_compact() -> InMemoryHandle {
+		let handle = QlogWriter::init_in_memory();
+
+		QlogWriter::set_record_format(RecordFormat::Compact);
+		QlogWriter::log_file_details(None, None, None, None, None, None, None);
+
+		handle
+	}
+
+	fn records(handle: &InMemoryHandle) -> Vec<Value> {
+		handle.flush();
+
+		parse_records(&handle.contents())
+	}
+
+	/// Splits the output into records and parses each with a strict JSON parser
+	fn parse_records(contents: &[u8]) -> Vec<Value> {
+		let mut records = contents.split(|b| *b == 0x1E);
+
+		assert_eq!(records.next(), Some(&[][..]), "The output should start with a record separator");
+
+		records.map(|record| {
+			assert_eq!(record.last(), Some(&0x0A), "Every record should end with a line feed");
+
+			let json = std::str::from_utf8(&record[..record.len() - 1]).unwrap();
+
+			assert!(!json.contains('\n'), "Compact records should be a single line");
+
+			serde_json::from_str(json).unwrap()
+		}).collect()
+	}
+
+	#[test]
+	fn in_memory_contents_are_the_logged_records() {
+		let _lock = lock_writer();
+		let handle = init_compact();
+
+		handle.flush();
+		let file_details = handle.contents();
+
+		let event = Event::custom("test", "event", serde_json::json!({ "value": 1 }), None);
+		QlogWriter::log_event(event.clone());
+
+		handle.flush();
+		let contents = handle.contents();
+
+		assert!(contents.starts_with(&file_details));
+		assert_eq!(&contents[file_details.len()..], event.to_json_seq_record().unwrap().as_bytes());
+
+		// Resets the buffer
+		let handle = QlogWriter::init_in_memory();
+		handle.flush();
+
+		assert!(handle.contents().is_empty());
+	}
+
+	#[test]
+	fn compact_records_round_trip() {
+		let _lock = lock_writer();
+		let handle = init_compact();
+
+		let text = "record\u{1E}separator\nand line feed";
+
+		QlogWriter::log_event(Event::custom("test", "first", serde_json::json!({ "text": text }), None));
+		QlogWriter::log_event(Event::custom("test", "second", serde_json::json!([1, 2, 3]), Some("group".into())));
+
+		let records = records(&handle);
+
+		assert_eq!(records.len(), 3);
+		assert!(records[0].get("file_schema").is_some());
+		assert_eq!(records[1]["name"], "test:first");
+		assert_eq!(records[1]["data"]["text"], text);
+		assert_eq!(records[2]["group_id"], "group");
+	}
+
+	#[test]
+	fn legacy_field_names() {
+		let _lock = lock_writer();
+		let handle = QlogWriter::init_in_memory();
+
+		QlogWriter::set_record_format(RecordFormat::Compact);
+		QlogWriter::set_field_names(FieldNames::Legacy);
+
+		let mut common_fields = CommonFields::default();
+		common_fields.set_protocol_types(vec!["QUIC".to_string()]);
+
+		QlogWriter::log_custom_file_details(QlogFileSeq::new(LogFile::new(None, None), TraceSeq::new(None, None, None, Some(common_fields), None)));
+
+		// The data of custom events is logged as is
+		QlogWriter::log_event(Event::custom("test", "custom", serde_json::json!({ "error_code_bytes": 1 }), None));
+
+		let records = records(&handle);
+		let common_fields = &records[0]["trace"]["common_fields"];
+
+		assert_eq!(common_fields["protocol_type"], serde_json::json!(["QUIC"]));
+		assert!(common_fields.get("protocol_types").is_none());
+		assert_eq!(records[1]["data"]["error_code_bytes"], 1);
+	}
+
+	/// The time of every record after the file details
+	fn times(records: &[Value]) -> Vec<&Value> {
+		records[1..].iter().map(|record| &record["time"]).collect()
+	}
+
+	#[test]
+	fn reorder_window_sorts_events_by_time() {
+		let _lock = lock_writer();
+		let handle = init_compact();
+
+		let clock = Arc::new(ManualClock::new(3000));
+		QlogWriter::set_clock(clock.clone());
+
+		let third = Event::custom("test", "third", Value::Null, None);
+		clock.set(1000);
+		let first = Event::custom("test", "first", Value::Null, None);
+		clock.set(2000);
+		let second = Event::custom("test", "second", Value::Null, None);
+
+		QlogWriter::set_clock(Arc::new(SystemClock));
+
+		QlogWriter::set_reorder_window(Some(2));
+		QlogWriter::log_event(third);
+		QlogWriter::log_event(first);
+		QlogWriter::log_event(second);
+
+		assert_eq!(times(&records(&handle)), [1000, 2000, 3000]);
+	}
+
+	#[test]
+	fn rotate_starts_a_new_file_with_the_file_details() {
+		let _lock = lock_writer();
+		let handle = init_compact();
+
+		let path = std::env::temp_dir().join(format!("qlog-rs-rotate-{}.sqlog", std::process::id()));
+
+		QlogWriter::log_event(Event::custom("test", "before", Value::Null, None));
+		QlogWriter::rotate(&path).unwrap();
+		QlogWriter::log_event(Event::custom("test", "after", Value::Null, None));
+		QlogWriter::flush();
+
+		let old_records = records(&handle);
+		let new_records = parse_records(&std::fs::read(&path).unwrap());
+
+		std::fs::remove_file(&path).unwrap();
+
+		assert_eq!(old_records.len(), 2);
+		assert_eq!(old_records[1]["name"], "test:before");
+		assert_eq!(new_records.len(), 2);
+		assert_eq!(new_records[0], old_records[0]);
+		assert_eq!(new_records[1]["name"], "test:after");
+	}
+
+	#[test]
+	fn sequence_numbers_increase_per_event() {
+		let _lock = lock_writer();
+		let handle = init_compact();
+
+		QlogWriter::log_event(Event::custom("test", "event", Value::Null, None));
+
+		QlogWriter::set_sequence_numbers(true);
+		QlogWriter::log_event(Event::custom("test", "event", Value::Null, None));
+		QlogWriter::log_event(Event::custom("test", "event", Value::Null, None));
+
+		// Enabling them again keeps counting
+		QlogWriter::set_sequence_numbers(true);
+		QlogWriter::log_event(Event::custom("test", "event", Value::Null, None));
+
+		QlogWriter::set_sequence_numbers(false);
+		QlogWriter::log_event(Event::custom("test", "event", Value::Null, None));
+
+		let records = records(&handle);
+		let seqs: Vec<&Value> = records.iter().map(|record| &record["seq"]).collect();
+
+		assert_eq!(seqs, [&Value::Null, &Value::Null, &Value::from(0), &Value::from(1), &Value::from(2), &Value::Null]);
+	}
+
+	#[test]
+	fn auto_file_details_are_logged_before_the_first_event() {
+		let _lock = lock_writer();
+		let handle = QlogWriter::init_in_memory();
+
+		QlogWriter::set_record_format(RecordFormat::Compact);
+		QlogWriter::set_auto_file_details(true);
+
+		let ((), reports) = diagnostic::capture(|| {
+			QlogWriter::log_event(Event::custom("test", "first", Value::Null, None));
+			QlogWriter::log_event(Event::custom("test", "second", Value::Null, None));
+		});
+
+		let records = records(&handle);
+
+		assert_eq!(records.len(), 3);
+		assert!(records[0].get("file_schema").is_some());
+		assert_eq!(records[1]["name"], "test:first");
+		assert_eq!(reports.len(), 1);
+	}
+
+	#[test]
+	fn group_limit_drops_events_with_a_single_diagnostic() {
+		let _lock = lock_writer();
+		let handle = init_compact();
+
+		QlogWriter::set_max_events_per_group(Some(2));
+
+		let ((), reports) = diagnostic::capture(|| {
+			for i in 0..5 {
+				QlogWriter::log_event(Event::custom("test", "event", serde_json::json!(i), Some("a".into())));
+			}
+
+			QlogWriter::log_event(Event::custom("test", "event", Value::Null, Some("b".into())));
+			QlogWriter::log_event(Event::custom("test", "event", Value::Null, None));
+		});
+
+		QlogWriter::set_max_events_per_group(None);
+
+		let records = records(&handle);
+		let groups: Vec<&Value> = records[1..].iter().map(|record| &record["group_id"]).collect();
+
+		assert_eq!(groups, [&Value::from("a"), &Value::from("a"), &Value::from("b"), &Value::Null]);
+		assert_eq!(reports.len(), 1);
+	}
+
+	#[test]
+	fn group_counts_are_bounded() {
+		let _lock = lock_writer();
+		let handle = init_compact();
+
+		QlogWriter::set_max_events_per_group(Some(1));
+
+		for i in 0..=QlogWriter::MAX_COUNTED_GROUPS {
+			QlogWriter::log_event(Event::custom("test", "event", Value::Null, Some(i.to_string().into())));
+		}
+
+		assert_eq!(QLOG_WRITER.lock().unwrap().group_event_counts.len(), QlogWriter::MAX_COUNTED_GROUPS);
+
+		// The least recently logged group was forgotten, so its events are counted from 0 again
+		QlogWriter::log_event(Event::custom("test", "event", Value::Null, Some("0".into())));
+
+		QlogWriter::set_max_events_per_group(None);
+
+		assert_eq!(records(&handle).len(), QlogWriter::MAX_COUNTED_GROUPS + 3);
+	}
+
+	#[cfg(feature = "moq-transfork")]
+	#[test]
+	fn reorder_window_holds_back_moq_events() {
+		let _lock = lock_writer();
+		let handle = init_compact();
+
+		let clock = Arc::new(ManualClock::new(1000));
+		QlogWriter::set_clock(clock.clone());
+
+		let custom = Event::custom("test", "custom", Value::Null, None);
+		clock.set(2000);
+		let moq = Event::moq_session_update_created(1000, 1);
+
+		QlogWriter::set_clock(Arc::new(SystemClock));
+
+		QlogWriter::set_reorder_window(Some(1));
+		QlogWriter::log_event(custom);
+		QlogWriter::log_event(moq);
+
+		assert_eq!(times(&records(&handle)), [1000, 2000]);
+	}
+
+	#[cfg(feature = "quic-10")]
+	mod quic_10 {
+		use crate::events::Event;
+		use crate::quic_10::data::{ConnectionCloseFrame, QuicBaseFrame, QuicFrame};
+		use crate::diagnostic;
+		use crate::quic_10::data::{Owner, PacketHeader, PacketType, PingFrame};
+		use crate::quic_10::events::{PacketSent, RecoveryMetricsUpdated};
+		use crate::quic_10::tracer::ConnectionTracer;
+		use crate::writer::{FieldNames, PacketNum, PacketNumSpace, QlogWriter, QLOG_WRITER};
+
+		use super::{init_compact, lock_writer, records};
+
+		fn header(packet_number: u64) -> PacketHeader {
+			PacketHeader::new(None, PacketType::OneRtt, None, Some(packet_number), None, None, None, None, None, None, None, None)
+		}
+
+		fn packet_num(packet_number: u64) -> PacketNum {
+			PacketNum::Number(PacketNumSpace::Data, packet_number)
+		}
+
+		fn ping() -> QuicFrame {
+			QuicFrame::QuicBaseFrame(QuicBaseFrame::PingFrame(PingFrame::new(None)))
+		}
+
+		/// Caches and logs the sent packets
+		fn send_packets(cid: &str, packet_numbers: impl Iterator<Item = u64> + Clone) {
+			for packet_number in packet_numbers.clone() {
+				QlogWriter::cache_quic_packet_sent(cid.to_string(), packet_num(packet_number), PacketSent::builder(header(packet_number)).build());
+			}
+
+			QlogWriter::log_quic_packets_sent(cid.to_string(), packet_numbers.map(packet_num).collect());
+		}
+
+		fn unacked_count() -> usize {
+			QLOG_WRITER.lock().unwrap().unacked_sent_quic_packets.len()
+		}
+
+		#[test]
+		fn unacked_packets_are_forgotten_when_acked_lost_or_closed() {
+			let _lock = lock_writer();
+			let _handle = init_compact();
+
+			let cid = "abcdef".to_string();
+
+			send_packets(&cid, 1..=4);
+			assert_eq!(unacked_count(), 4);
+
+			let ((), reports) = diagnostic::capture(|| QlogWriter::log_quic_packets_acked(cid.clone(), PacketNumSpace::Data, vec![1, 1, 9]));
+			assert_eq!(unacked_count(), 3);
+			assert_eq!(reports.len(), 2);
+
+			QlogWriter::log_event(Event::quic_10_packet_lost(Some(header(2)), None, None, None, Some(cid.clone())));
+			assert_eq!(unacked_count(), 2);
+
+			QlogWriter::log_event(Event::quic_10_connection_closed_idle_timeout(Owner::Local, Some(cid.clone())));
+			assert_eq!(unacked_count(), 0);
+		}
+
+		#[test]
+		fn acked_packets_carry_their_send_time() {
+			let _lock = lock_writer();
+			let handle = init_compact();
+
+			let cid = "abcdef".to_string();
+
+			send_packets(&cid, 0..2);
+			QlogWriter::log_quic_packets_acked(cid.clone(), PacketNumSpace::Data, vec![0, 1]);
+
+			let records = records(&handle);
+			let largest_sent = records.iter().find(|record| record["name"] == "quic-10:packet_sent" && record["data"]["header"]["packet_number"] == 1).unwrap();
+			let packets_acked = records.iter().find(|record| record["name"] == "quic-10:packets_acked").unwrap();
+
+			assert!(packets_acked["data"]["largest_acked_sent_time"].is_number());
+			assert_eq!(packets_acked["data"]["largest_acked_sent_time"], largest_sent["time"]);
+		}
+
+		#[test]
+		fn unacked_packets_of_evicted_connections_are_forgotten() {
+			let _lock = lock_writer();
+			let _handle = init_compact();
+
+			QlogWriter::set_max_cached_connections(Some(2));
+
+			// A peer flooding connection IDs, whose packets are never acknowledged
+			let ((), reports) = diagnostic::capture(|| {
+				for i in 0..100 {
+					send_packets(&format!("flood{i}"), 0..3);
+				}
+			});
+
+			assert_eq!(unacked_count(), 6);
+			assert_eq!(QLOG_WRITER.lock().unwrap().cached_connection_uses.len(), 2);
+			assert_eq!(reports.len(), 98);
+
+			// Acknowledging everything stops tracking the connection
+			QlogWriter::log_quic_packets_acked("flood99".to_string(), PacketNumSpace::Data, vec![0, 1, 2]);
+			assert!(!QLOG_WRITER.lock().unwrap().cached_connection_uses.contains_key("flood99"));
+
+			QlogWriter::set_max_cached_connections(None);
+			QlogWriter::clear_caches();
+		}
+
+		#[test]
+		fn evicted_packets_are_ignored() {
+			let _lock = lock_writer();
+			let _handle = init_compact();
+
+			QlogWriter::set_max_cached_connections(Some(1));
+
+			let ((), reports) = diagnostic::capture(|| {
+				QlogWriter::cache_quic_packet_sent("abcdef".to_string(), packet_num(0), PacketSent::builder(header(0)).build());
+				QlogWriter::cache_quic_frames_processed("abcdef".to_string(), packet_num(0));
+				QlogWriter::cache_quic_packet_sent("ghijkl".to_string(), packet_num(0), PacketSent::builder(header(0)).build());
+				assert_eq!(QlogWriter::cached_packet_counts(), (1, 0));
+
+				// The packets of the first connection were evicted
+				QlogWriter::quic_packet_sent_add_frame("abcdef".to_string(), packet_num(0), ping());
+				QlogWriter::quic_frames_processed_add_frame("abcdef".to_string(), packet_num(0), ping());
+				QlogWriter::quic_frames_processed_add_application_frame("abcdef".to_string(), packet_num(0), serde_json::json!({ "frame_type": "data" }));
+
+				QlogWriter::log_quic_packets_sent("ghijkl".to_string(), vec![packet_num(0)]);
+			});
+
+			assert_eq!(QlogWriter::cached_packet_counts(), (0, 0));
+			assert!(reports[0].starts_with("Cached connection limit reached, dropped 2 cached packets"));
+			assert_eq!(reports.len(), 4);
+
+			QlogWriter::set_max_cached_connections(None);
+			QlogWriter::clear_caches();
+		}
+
+		#[test]
+		fn connection_uses_are_only_tracked_with_a_limit() {
+			let _lock = lock_writer();
+			let _handle = init_compact();
+
+			QlogWriter::cache_quic_packet_sent("abcdef".to_string(), packet_num(0), PacketSent::builder(header(0)).build());
+
+			assert!(QLOG_WRITER.lock().unwrap().cached_connection_uses.is_empty());
+
+			QlogWriter::clear_caches();
+		}
+
+		#[test]
+		fn frames_processed_are_logged_incrementally() {
+			let _lock = lock_writer();
+			let handle = init_compact();
+
+			let ((), reports) = diagnostic::capture(|| {
+				// A zero-length connection ID is shorter than the prefix shown in diagnostics
+				QlogWriter::cache_quic_frames_processed("".to_string(), packet_num(0));
+				QlogWriter::quic_frames_processed_add_frame("".to_string(), packet_num(0), ping());
+				QlogWriter::quic_frames_processed_add_frame("".to_string(), packet_num(0), ping());
+				QlogWriter::log_quic_frames_processed("".to_string(), packet_num(0));
+
+				// Not cached (anymore)
+				QlogWriter::quic_frames_processed_add_frame("ab".to_string(), packet_num(0), ping());
+				QlogWriter::log_quic_frames_processed("ab".to_string(), packet_num(0));
+			});
+
+			let records = records(&handle);
+
+			assert_eq!(records.len(), 2);
+			assert_eq!(records[1]["name"], "quic-10:frames_processed");
+			assert_eq!(records[1]["data"]["frames"].as_array().unwrap().len(), 2);
+			assert_eq!(reports.len(), 2);
+		}
+
+		#[test]
+		fn application_frames_are_logged_next_to_the_frames() {
+			let _lock = lock_writer();
+			let handle = init_compact();
+
+			let data = serde_json::json!({ "frame_type": "data", "length": 10 });
+			let headers = serde_json::json!({ "frame_type": "headers" });
+
+			QlogWriter::log_event(Event::quic_10_frames_processed_with_application_frames(vec![ping()], None, vec![headers.clone()], None));
+
+			QlogWriter::cache_quic_frames_processed("abcdef".to_string(), packet_num(0));
+			QlogWriter::quic_frames_processed_add_frame("abcdef".to_string(), packet_num(0), ping());
+			QlogWriter::quic_frames_processed_add_application_frame("abcdef".to_string(), packet_num(0), headers.clone());
+			QlogWriter::quic_frames_processed_add_application_frame("abcdef".to_string(), packet_num(0), data.clone());
+			QlogWriter::log_quic_frames_processed("abcdef".to_string(), packet_num(0));
+
+			QlogWriter::log_event(Event::quic_10_frames_processed(vec![ping()], None, None));
+
+			let records = records(&handle);
+
+			assert_eq!(records[1]["data"]["application_frames"], serde_json::json!([headers]));
+			assert_eq!(records[2]["data"]["application_frames"], serde_json::json!([headers, data]));
+			assert!(records[3]["data"].get("application_frames").is_none());
+		}
+
+		#[test]
+		fn unchanged_recovery_metrics_are_skipped() {
+			let _lock = lock_writer();
+			let handle = init_compact();
+
+			QlogWriter::set_recovery_metrics_dedup(true);
+
+			for (congestion_window, cid) in [(10, "a"), (10, "a"), (10, "b"), (12, "a"), (12, "a")] {
+				QlogWriter::log_event(Event::quic_10_recovery_metrics_updated(None, None, None, None, None, Some(congestion_window), None, None, None, None, Some(cid.to_string())));
+			}
+
+			QlogWriter::log_event(Event::quic_10_connection_closed_idle_timeout(Owner::Local, Some("a".to_string())));
+			QlogWriter::log_event(Event::quic_10_connection_closed_idle_timeout(Owner::Local, Some("b".to_string())));
+
+			assert!(QLOG_WRITER.lock().unwrap().last_recovery_metrics.as_ref().unwrap().is_empty());
+
+			QlogWriter::set_recovery_metrics_dedup(false);
+
+			let records = records(&handle);
+			let metrics: Vec<(&str, u64)> = records.iter()
+				.filter(|record| record["name"] == "quic-10:recovery_metrics_updated")
+				.map(|record| (record["group_id"].as_str().unwrap(), record["data"]["congestion_window"].as_u64().unwrap()))
+				.collect();
+
+			assert_eq!(metrics, [("a", 10), ("b", 10), ("a", 12)]);
+		}
+
+		#[test]
+		fn frames_are_truncated_to_the_max_record_size() {
+			let _lock = lock_writer();
+			let handle = init_compact();
+
+			QlogWriter::set_max_record_size(Some(300));
+
+			let ((), reports) = diagnostic::capture(|| {
+				QlogWriter::log_event(Event::quic_10_frames_processed(vec![ping(); 100], None, None));
+				QlogWriter::log_event(Event::quic_10_frames_processed(vec![ping(); 2], None, None));
+
+				// Events without frames are logged as is
+				QlogWriter::log_event(Event::custom("test", "large", serde_json::json!("x".repeat(400)), None));
+			});
+
+			let records = records(&handle);
+			let contents = handle.contents();
+
+			let truncated_frames = records[1]["data"]["frames"].as_array().unwrap().len();
+
+			assert!(truncated_frames > 0 && truncated_frames < 100);
+			assert_eq!(records[1]["data"]["truncated"], true);
+			assert_eq!(records[2]["data"]["frames"].as_array().unwrap().len(), 2);
+			assert!(records[2]["data"].get("truncated").is_none());
+			assert_eq!(records[3]["name"], "test:large");
+			assert_eq!(reports.len(), 1);
+
+			let record_lengths: Vec<usize> = contents.split(|b| *b == 0x1E).skip(2).map(|record| record.len() - 1).collect();
+
+			assert!(record_lengths[0] <= 300 && record_lengths[1] <= 300 && record_lengths[2] > 300);
+		}
+
+		#[test]
+		fn connection_tracer_logs_with_its_connection_id() {
+			let _lock = lock_writer();
+			let handle = init_compact();
+
+			let tracer = ConnectionTracer::new("abcdef".to_string());
+
+			tracer.cache_packet_sent(packet_num(0), PacketSent::builder(header(0)).build());
+			tracer.packet_sent_add_frame(packet_num(0), ping());
+			tracer.packets_sent(vec![packet_num(0)]);
+			tracer.packets_acked(PacketNumSpace::Data, vec![0]);
+			tracer.recovery_metrics_updated(RecoveryMetricsUpdated::new(None, None, None, None, None, Some(10), None, None, None, None));
+			tracer.connection_closed_idle_timeout(Owner::Local);
+
+			let records = records(&handle);
+			let events: Vec<(&str, &str)> = records[1..].iter().map(|record| (record["name"].as_str().unwrap(), record["group_id"].as_str().unwrap())).collect();
+
+			assert_eq!(events, [
+				("quic-10:packet_sent", "abcdef"),
+				("quic-10:packets_acked", "abcdef"),
+				("quic-10:recovery_metrics_updated", "abcdef"),
+				("quic-10:connection_closed", "abcdef")
+			]);
+			assert_eq!(records[1]["data"]["frames"][0]["frame_type"], "ping");
+		}
+
+		#[test]
+		fn closed_connections_are_no_longer_counted() {
+			let _lock = lock_writer();
+			let _handle = init_compact();
+
+			QlogWriter::set_max_events_per_group(Some(10));
+
+			QlogWriter::log_event(Event::quic_10_connection_closed_idle_timeout(Owner::Local, Some("abcdef".to_string())));
+
+			assert!(QLOG_WRITER.lock().unwrap().group_event_counts.is_empty());
+
+			QlogWriter::set_max_events_per_group(None);
+		}
+
+		#[test]
+		fn legacy_error_code_names() {
+			let _lock = lock_writer();
+			let handle = init_compact();
+
+			QlogWriter::set_field_names(FieldNames::Legacy);
+
+			let frame = QuicFrame::QuicBaseFrame(QuicBaseFrame::ConnectionCloseFrame(ConnectionCloseFrame::with_application_code(7, None, None)));
+
+			QlogWriter::log_event(Event::quic_10_frames_processed(vec![frame], None, None));
+
+			let records = records(&handle);
+			let frame = &records[1]["data"]["frames"][0];
+
+			assert_eq!(frame["error_code_value"], 7);
+			assert!(frame.get("error_code_bytes").is_none());
+		}
+	}
+}